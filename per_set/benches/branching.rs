@@ -0,0 +1,50 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use per_set::PerMap;
+use rustc_hash::FxBuildHasher;
+
+const SIZE: u64 = 65536;
+
+fn build<const B: usize>(size: u64) -> PerMap<u64, u64, FxBuildHasher, B> {
+    (0..size).fold(PerMap::default(), |m, k| m.insert(k, k))
+}
+
+fn bench<const B: usize>(c: &mut Criterion) {
+    let map = build::<B>(SIZE);
+
+    c.bench_with_input(BenchmarkId::new("get", B), &map, |b, map| {
+        b.iter(|| {
+            (0..SIZE)
+                .filter(|k| map.get(black_box(k)).is_some())
+                .count()
+        });
+    });
+    c.bench_with_input(BenchmarkId::new("insert", B), &map, |b, map| {
+        b.iter(|| {
+            (SIZE..SIZE + 1024)
+                .map(|k| map.insert(black_box(k), k).len())
+                .sum::<usize>()
+        });
+    });
+    c.bench_with_input(BenchmarkId::new("remove", B), &map, |b, map| {
+        b.iter(|| {
+            (0..1024)
+                .map(|k| map.remove(black_box(&k)).len())
+                .sum::<usize>()
+        });
+    });
+    c.bench_function(&format!("build/{B}"), |b| {
+        b.iter(|| build::<B>(black_box(SIZE)));
+    });
+}
+
+fn branching(c: &mut Criterion) {
+    bench::<8>(c);
+    bench::<16>(c);
+    bench::<32>(c);
+    bench::<64>(c);
+}
+
+criterion_group!(benches, branching);
+criterion_main!(benches);
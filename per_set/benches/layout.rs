@@ -0,0 +1,41 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use per_set::PerMap;
+
+const SIZES: [u64; 4] = [4, 64, 1024, 65536];
+
+fn build(size: u64) -> PerMap<u64, u64> {
+    (0..size).fold(PerMap::empty(), |m, k| m.insert(k, k))
+}
+
+fn get(c: &mut Criterion) {
+    let map = build(1024);
+    c.bench_function("get/1024", |b| {
+        b.iter(|| {
+            (0..1024)
+                .filter(|k| map.get(black_box(k)).is_some())
+                .count()
+        });
+    });
+}
+
+fn insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert");
+    for size in SIZES {
+        group.bench_with_input(BenchmarkId::new("persistent", size), &size, |b, &size| {
+            b.iter(|| build(black_box(size)));
+        });
+        group.bench_with_input(BenchmarkId::new("collect", size), &size, |b, &size| {
+            b.iter(|| {
+                (0..black_box(size))
+                    .map(|k| (k, k))
+                    .collect::<PerMap<_, _>>()
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, get, insert);
+criterion_main!(benches);
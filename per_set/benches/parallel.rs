@@ -0,0 +1,64 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use per_set::PerMap;
+use rayon::prelude::*;
+
+const SIZES: [u64; 2] = [1 << 20, 1 << 22];
+
+fn build(keys: impl Iterator<Item = u64>) -> PerMap<u64, u64> {
+    keys.map(|k| (k, k)).collect()
+}
+
+fn iterate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("iter");
+    group.sample_size(10);
+    for size in SIZES {
+        let map = build(0..size);
+        group.bench_with_input(BenchmarkId::new("sequential", size), &map, |b, map| {
+            b.iter(|| map.iter().map(|entry| entry.1).sum::<u64>());
+        });
+        group.bench_with_input(BenchmarkId::new("parallel", size), &map, |b, map| {
+            b.iter(|| map.par_iter().map(|entry| entry.1).sum::<u64>());
+        });
+    }
+    group.finish();
+}
+
+fn union(c: &mut Criterion) {
+    let mut group = c.benchmark_group("union");
+    group.sample_size(10);
+    for size in SIZES {
+        let left = build((0..size).map(|k| k * 2));
+        let right = build((0..size).map(|k| k * 3));
+        group.bench_function(BenchmarkId::new("sequential", size), |b| {
+            b.iter(|| black_box(&left).union(black_box(&right)));
+        });
+        group.bench_function(BenchmarkId::new("parallel", size), |b| {
+            b.iter(|| black_box(&left).par_union(black_box(&right)));
+        });
+    }
+    group.finish();
+}
+
+fn collect(c: &mut Criterion) {
+    let mut group = c.benchmark_group("collect");
+    group.sample_size(10);
+    for size in SIZES {
+        group.bench_with_input(BenchmarkId::new("sequential", size), &size, |b, &size| {
+            b.iter(|| build(0..black_box(size)));
+        });
+        group.bench_with_input(BenchmarkId::new("parallel", size), &size, |b, &size| {
+            b.iter(|| {
+                (0..black_box(size))
+                    .into_par_iter()
+                    .map(|k| (k, k))
+                    .collect::<PerMap<_, _>>()
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, iterate, union, collect);
+criterion_main!(benches);
@@ -0,0 +1,44 @@
+//! Prints how much memory maps of a few sizes take, counted by a global
+//! allocator that tracks live bytes.
+//!
+//! Run with `cargo run --release -p per_set --example layout`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use per_set::PerMap;
+
+struct CountingAllocator;
+
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LIVE_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const SIZES: [u64; 4] = [4, 64, 1024, 65536];
+
+fn main() {
+    for size in SIZES {
+        let before = LIVE_BYTES.load(Ordering::Relaxed);
+        let map = (0..size).fold(PerMap::empty(), |m, k| m.insert(k, k));
+        let bytes = LIVE_BYTES.load(Ordering::Relaxed) - before;
+        println!(
+            "{size} entries: {bytes} bytes, {} bytes per entry",
+            bytes / map.len()
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use std::{
+    borrow::Borrow,
+    hash::{BuildHasher, Hash, Hasher},
+    sync::Arc,
+};
+
+use rustc_hash::FxBuildHasher;
+
+use crate::{iter, PerMap};
+
+pub struct PerBag<K, S = FxBuildHasher> {
+    data: PerMap<K, usize, S>,
+    len: usize,
+}
+
+impl<K> PerBag<K> {
+    #[must_use]
+    pub fn empty() -> Self {
+        Self::with_hasher(FxBuildHasher)
+    }
+}
+
+impl<K> Default for PerBag<K> {
+    fn default() -> Self {
+        PerBag::<K>::empty()
+    }
+}
+
+impl<K, S> PerBag<K, S> {
+    #[must_use]
+    pub fn with_hasher(hash_builder: S) -> Self {
+        PerBag {
+            data: PerMap::with_hasher(hash_builder),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn distinct_len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn iter(&self) -> Iter<'_, K> {
+        Iter(self.data.iter())
+    }
+}
+
+impl<K, S> PerBag<K, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    #[must_use]
+    pub fn insert(&self, key: K) -> Self {
+        self.insert_many(key, 1)
+    }
+
+    #[must_use]
+    pub fn insert_many(&self, key: K, count: usize) -> Self {
+        if count == 0 {
+            return self.clone();
+        }
+        PerBag {
+            data: self
+                .data
+                .update(key, |current| Some(current.copied().unwrap_or(0) + count)),
+            len: self.len + count,
+        }
+    }
+
+    #[must_use]
+    pub fn remove<Q>(&self, key: &Q) -> Self
+    where
+        K: Borrow<Q> + Clone,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.remove_many(key, 1)
+    }
+
+    #[must_use]
+    pub fn remove_many<Q>(&self, key: &Q, count: usize) -> Self
+    where
+        K: Borrow<Q> + Clone,
+        Q: Eq + Hash + ?Sized,
+    {
+        let mut removed = 0;
+        let data = self.data.modify(key, |current| {
+            removed = (*current).min(count);
+            (removed > 0).then(|| (*current > removed).then(|| *current - removed))
+        });
+        PerBag {
+            data,
+            len: self.len - removed,
+        }
+    }
+
+    #[must_use]
+    pub fn count<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.data.get(key).copied().unwrap_or(0)
+    }
+
+    #[must_use]
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.data.get(key).is_some()
+    }
+
+    #[must_use]
+    pub fn sum(&self, other: &PerBag<K, S>) -> Self
+    where
+        K: Clone,
+    {
+        PerBag {
+            data: self.data.union_with(&other.data, |_, l, r| l + r),
+            len: self.len + other.len,
+        }
+    }
+
+    #[must_use]
+    pub fn union(&self, other: &PerBag<K, S>) -> Self
+    where
+        K: Clone,
+    {
+        PerBag::counted(self.data.union_with(&other.data, |_, l, r| *l.max(r)))
+    }
+
+    #[must_use]
+    pub fn intersection(&self, other: &PerBag<K, S>) -> Self
+    where
+        K: Clone,
+    {
+        PerBag::counted(
+            self.data
+                .intersection_with(&other.data, |_, l, r| *l.min(r)),
+        )
+    }
+
+    fn counted(data: PerMap<K, usize, S>) -> Self {
+        let len = data.values().sum();
+        PerBag { data, len }
+    }
+}
+
+impl<K, S: Clone> Clone for PerBag<K, S> {
+    fn clone(&self) -> Self {
+        PerBag {
+            data: self.data.with_data(Arc::clone(&self.data.data)),
+            len: self.len,
+        }
+    }
+}
+
+impl<K: Eq, S> PartialEq for PerBag<K, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.data == other.data
+    }
+}
+
+impl<K: Eq, S> Eq for PerBag<K, S> {}
+
+impl<K: Hash, S: BuildHasher> Hash for PerBag<K, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.data.hash(state);
+    }
+}
+
+impl<K, S> FromIterator<K> for PerBag<K, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Clone + Default,
+{
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(PerBag::with_hasher(S::default()), |bag, key| {
+                bag.insert(key)
+            })
+    }
+}
+
+pub struct Iter<'a, K>(iter::Iter<'a, K, usize>);
+
+impl<'a, K> Iterator for Iter<'a, K> {
+    type Item = (&'a K, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|entry| (&entry.0, entry.1))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K> ExactSizeIterator for Iter<'_, K> {}
+
+impl<'a, K, S> IntoIterator for &'a PerBag<K, S> {
+    type Item = (&'a K, usize);
+
+    type IntoIter = Iter<'a, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
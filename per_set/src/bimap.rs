@@ -0,0 +1,194 @@
+use std::{
+    borrow::Borrow,
+    error::Error,
+    fmt::{self, Display, Formatter},
+    hash::{BuildHasher, Hash},
+    sync::Arc,
+};
+
+use rustc_hash::FxBuildHasher;
+
+use crate::{iter, PerMap};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BiMapError<L, R> {
+    LeftTaken { left: L, right: R },
+    RightTaken { left: L, right: R },
+}
+
+impl<L, R> BiMapError<L, R> {
+    pub fn into_pair(self) -> (L, R) {
+        match self {
+            BiMapError::LeftTaken { left, right } | BiMapError::RightTaken { left, right } => {
+                (left, right)
+            }
+        }
+    }
+}
+
+impl<L, R> Display for BiMapError<L, R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BiMapError::LeftTaken { .. } => {
+                write!(f, "left value is already paired with another right value")
+            }
+            BiMapError::RightTaken { .. } => {
+                write!(f, "right value is already paired with another left value")
+            }
+        }
+    }
+}
+
+impl<L: fmt::Debug, R: fmt::Debug> Error for BiMapError<L, R> {}
+
+pub struct PerBiMap<L, R, S = FxBuildHasher> {
+    left: PerMap<L, R, S>,
+    right: PerMap<R, L, S>,
+}
+
+impl<L, R> PerBiMap<L, R> {
+    #[must_use]
+    pub fn empty() -> Self {
+        Self::with_hasher(FxBuildHasher)
+    }
+}
+
+impl<L, R> Default for PerBiMap<L, R> {
+    fn default() -> Self {
+        PerBiMap::<L, R>::empty()
+    }
+}
+
+impl<L, R, S: Clone> PerBiMap<L, R, S> {
+    #[must_use]
+    pub fn with_hasher(hash_builder: S) -> Self {
+        PerBiMap {
+            left: PerMap::with_hasher(hash_builder.clone()),
+            right: PerMap::with_hasher(hash_builder),
+        }
+    }
+}
+
+impl<L, R, S> PerBiMap<L, R, S> {
+    pub fn len(&self) -> usize {
+        self.left.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.left.is_empty()
+    }
+
+    pub fn iter(&self) -> Iter<'_, L, R> {
+        Iter(self.left.iter())
+    }
+}
+
+impl<L, R, S> PerBiMap<L, R, S>
+where
+    L: Eq + Hash + Clone,
+    R: Eq + Hash + Clone,
+    S: BuildHasher + Clone,
+{
+    pub fn insert(&self, left: L, right: R) -> Result<Self, BiMapError<L, R>> {
+        match (self.left.get(&left), self.right.get(&right)) {
+            (Some(current), _) if *current == right => Ok(self.clone()),
+            (Some(_), _) => Err(BiMapError::LeftTaken { left, right }),
+            (None, Some(_)) => Err(BiMapError::RightTaken { left, right }),
+            (None, None) => Ok(PerBiMap {
+                left: self.left.insert(left.clone(), right.clone()),
+                right: self.right.insert(right, left),
+            }),
+        }
+    }
+
+    #[must_use]
+    pub fn get_by_left<Q>(&self, left: &Q) -> Option<&R>
+    where
+        L: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.left.get(left)
+    }
+
+    #[must_use]
+    pub fn get_by_right<Q>(&self, right: &Q) -> Option<&L>
+    where
+        R: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.right.get(right)
+    }
+
+    #[must_use]
+    pub fn remove_left<Q>(&self, left: &Q) -> Self
+    where
+        L: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        match self.left.get(left) {
+            Some(right) => PerBiMap {
+                left: self.left.remove(left),
+                right: self.right.remove(right),
+            },
+            None => self.clone(),
+        }
+    }
+
+    #[must_use]
+    pub fn remove_right<Q>(&self, right: &Q) -> Self
+    where
+        R: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        match self.right.get(right) {
+            Some(left) => PerBiMap {
+                left: self.left.remove(left),
+                right: self.right.remove(right),
+            },
+            None => self.clone(),
+        }
+    }
+}
+
+impl<L, R, S: Clone> Clone for PerBiMap<L, R, S> {
+    fn clone(&self) -> Self {
+        PerBiMap {
+            left: self.left.with_data(Arc::clone(&self.left.data)),
+            right: self.right.with_data(Arc::clone(&self.right.data)),
+        }
+    }
+}
+
+impl<L: Eq, R: Eq, S> PartialEq for PerBiMap<L, R, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.left == other.left
+    }
+}
+
+impl<L: Eq, R: Eq, S> Eq for PerBiMap<L, R, S> {}
+
+pub struct Iter<'a, L, R>(iter::Iter<'a, L, R>);
+
+impl<'a, L, R> Iterator for Iter<'a, L, R> {
+    type Item = (&'a L, &'a R);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|entry| (&entry.0, &entry.1))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<L, R> ExactSizeIterator for Iter<'_, L, R> {}
+
+impl<'a, L, R, S> IntoIterator for &'a PerBiMap<L, R, S> {
+    type Item = (&'a L, &'a R);
+
+    type IntoIter = Iter<'a, L, R>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
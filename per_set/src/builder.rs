@@ -0,0 +1,125 @@
+use std::hash::{BuildHasher, Hash};
+
+use rustc_hash::FxBuildHasher;
+
+use crate::{
+    nodes::{BitShifter, Node},
+    PerMap, PerSet,
+};
+
+pub struct PerMapBuilder<K, V, S = FxBuildHasher, const B: usize = 16>(PerMap<K, V, S, B>);
+
+impl<K, V> PerMapBuilder<K, V, FxBuildHasher> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_hasher(FxBuildHasher)
+    }
+}
+
+impl<K, V, const B: usize> Default for PerMapBuilder<K, V, FxBuildHasher, B> {
+    fn default() -> Self {
+        Self::with_hasher(FxBuildHasher)
+    }
+}
+
+impl<K, V, S, const B: usize> PerMapBuilder<K, V, S, B> {
+    pub fn with_hasher(hash_builder: S) -> Self {
+        PerMapBuilder(PerMap::with_hasher(hash_builder))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    #[must_use]
+    pub fn build(self) -> PerMap<K, V, S, B> {
+        self.0
+    }
+}
+
+impl<K, V, S, const B: usize> PerMapBuilder<K, V, S, B>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    pub fn insert(&mut self, key: K, value: V) {
+        self.0.insert_mut(key, value);
+    }
+}
+
+impl<K, V, S, const B: usize> PerMap<K, V, S, B> {
+    #[must_use]
+    pub fn into_builder(self) -> PerMapBuilder<K, V, S, B> {
+        PerMapBuilder(self)
+    }
+}
+
+impl<K, V, S, const B: usize> PerMap<K, V, S, B>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    fn insert_mut(&mut self, key: K, value: V) {
+        let hash = self.hasher.hash_one(&key);
+        let address = BitShifter::new(hash);
+        Node::insert_mut(&mut self.data, key, value, address);
+    }
+}
+
+impl<K, V, S, const B: usize> Extend<(K, V)> for PerMapBuilder<K, V, S, B>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        self.0.extend(iter);
+    }
+}
+
+impl<K, V, S, const B: usize> Extend<(K, V)> for PerMap<K, V, S, B>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (key, value) in iter {
+            self.insert_mut(key, value);
+        }
+    }
+}
+
+impl<K, V, S, const B: usize> FromIterator<(K, V)> for PerMap<K, V, S, B>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut map = PerMap::with_hasher(S::default());
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K, S, const B: usize> Extend<K> for PerSet<K, S, B>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    fn extend<T: IntoIterator<Item = K>>(&mut self, iter: T) {
+        self.0.extend(iter.into_iter().map(|key| (key, ())));
+    }
+}
+
+impl<K, S, const B: usize> FromIterator<K> for PerSet<K, S, B>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn from_iter<T: IntoIterator<Item = K>>(iter: T) -> Self {
+        PerSet(iter.into_iter().map(|key| (key, ())).collect())
+    }
+}
@@ -0,0 +1,409 @@
+use std::{
+    borrow::Borrow,
+    hash::{BuildHasher, Hash},
+    sync::Arc,
+};
+
+use smallvec::smallvec;
+
+use crate::{
+    nodes::{BitShifter, Children, Node, TakeRight},
+    PerMap,
+};
+
+/// A position inside a map that remembers the branches leading to it, so
+/// moving to a nearby key only climbs up to the closest common branch.
+///
+/// The cursor walks the entries in the same order as [`PerMap::iter`]. Edits
+/// made through it are kept inside the cursor and only copy the branches on
+/// the way to them, and [`Cursor::commit`] turns all of them into a single new
+/// map. The map the cursor was created from is never changed.
+pub struct Cursor<K, V, S, const B: usize = 16> {
+    hasher: S,
+    /// Branches from the root down to the cursor. The root frame is never
+    /// popped.
+    stack: Vec<Frame<K, V, B>>,
+    /// Contents of the current slot of the innermost branch.
+    focus: Option<Arc<Node<K, V, B>>>,
+    /// Whether the focus differs from what the innermost branch holds.
+    edited: bool,
+    /// Entry of the focused leaf the cursor points at.
+    position: usize,
+}
+
+struct Frame<K, V, const B: usize> {
+    branch: Arc<Node<K, V, B>>,
+    /// Slot the cursor went through, or `B` once it has run past the end.
+    index: usize,
+    /// Whether the branch differs from what its parent holds.
+    edited: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Spot {
+    Found,
+    Vacant,
+    Beside,
+}
+
+impl<K, V, S: Clone, const B: usize> PerMap<K, V, S, B> {
+    /// Returns a cursor standing at the first entry of the map.
+    pub fn cursor(&self) -> Cursor<K, V, S, B> {
+        let mut cursor = Cursor {
+            hasher: self.hasher.clone(),
+            stack: vec![Frame {
+                branch: Arc::clone(&self.data),
+                index: B,
+                edited: false,
+            }],
+            focus: None,
+            edited: false,
+            position: 0,
+        };
+        cursor.first_from(0);
+        cursor
+    }
+}
+
+impl<K, V, S, const B: usize> Cursor<K, V, S, B> {
+    #[must_use]
+    pub fn current(&self) -> Option<(&K, &V)> {
+        match self.focus.as_deref() {
+            Some(Node::Leaf { data, .. }) => {
+                data.get(self.position).map(|entry| (&entry.0, &entry.1))
+            }
+            _ => None,
+        }
+    }
+
+    /// Indices of the children taken from the root down to the current entry.
+    /// Empty once the cursor has run past the last entry.
+    pub fn path(&self) -> impl Iterator<Item = usize> + '_ {
+        self.stack
+            .iter()
+            .map(|frame| frame.index)
+            .filter(|index| *index < B)
+    }
+
+    /// Replaces the value of the current entry and returns the old entry.
+    pub fn set_value(&mut self, value: V) -> Option<Arc<(K, V)>>
+    where
+        K: Clone,
+    {
+        let Some(Node::Leaf { hash, data, .. }) = self.focus.as_deref() else {
+            return None;
+        };
+        let mut data = data.clone();
+        let old = data.get_mut(self.position)?;
+        let old = std::mem::replace(old, Arc::new((old.0.clone(), value)));
+        self.focus = Some(Arc::new(Node::leaf(*hash, data)));
+        self.edited = true;
+        Some(old)
+    }
+
+    /// Removes the current entry and moves to the one that followed it.
+    pub fn remove_current(&mut self) -> Option<Arc<(K, V)>> {
+        let Some(Node::Leaf { hash, data, .. }) = self.focus.as_deref() else {
+            return None;
+        };
+        if self.position >= data.len() {
+            return None;
+        }
+        let mut data = data.clone();
+        let removed = data.remove(self.position);
+        let remaining = data.len();
+        self.focus = (remaining > 0).then(|| Arc::new(Node::leaf(*hash, data)));
+        self.edited = true;
+        if self.position >= remaining {
+            self.first_from(self.index() + 1);
+        }
+        Some(removed)
+    }
+
+    /// Turns all the edits made through the cursor into a new map.
+    #[must_use]
+    pub fn commit(mut self) -> PerMap<K, V, S, B> {
+        while self.stack.len() > 1 {
+            self.pop();
+        }
+        self.flush();
+        let root = self.stack.swap_remove(0);
+        PerMap {
+            data: root.branch,
+            hasher: self.hasher,
+        }
+    }
+
+    fn index(&self) -> usize {
+        self.stack.last().unwrap().index
+    }
+
+    fn advance(&mut self) {
+        match self.focus.as_deref() {
+            Some(Node::Leaf { data, .. }) if self.position + 1 < data.len() => self.position += 1,
+            _ => self.first_from(self.index() + 1),
+        }
+    }
+
+    /// Moves to the first entry stored in the slots of the innermost branch
+    /// from `start` on, climbing up when the branch has nothing more. Stops
+    /// past the end when the whole map has been visited.
+    fn first_from(&mut self, mut start: usize) {
+        loop {
+            let data = children(&self.stack.last().unwrap().branch);
+            match (start..B).find(|k| data.get(*k).is_some()) {
+                Some(index) => {
+                    self.enter(index);
+                    match self.focus.as_deref() {
+                        Some(Node::Branch { .. }) => {
+                            self.descend();
+                            start = 0;
+                        }
+                        _ => return,
+                    }
+                }
+                None if self.stack.len() == 1 => return self.enter(B),
+                None => {
+                    self.pop();
+                    start = self.index() + 1;
+                }
+            }
+        }
+    }
+
+    /// Points the cursor at another slot of the innermost branch.
+    fn enter(&mut self, index: usize) {
+        self.flush();
+        let frame = self.stack.last_mut().unwrap();
+        frame.index = index;
+        self.focus = if index < B {
+            children(&frame.branch).get(index).cloned()
+        } else {
+            None
+        };
+        self.position = 0;
+    }
+
+    /// Makes the focused branch the innermost one. Its slot has to be entered
+    /// afterwards.
+    fn descend(&mut self) {
+        let branch = self.focus.take().unwrap();
+        self.stack.push(Frame {
+            branch,
+            index: B,
+            edited: self.edited,
+        });
+        self.edited = false;
+    }
+
+    /// Leaves the innermost branch, which becomes the focus of its parent. An
+    /// edited branch is collapsed the same way `Node::compact` does it.
+    fn pop(&mut self) {
+        self.flush();
+        let frame = self.stack.pop().unwrap();
+        let data = children(&frame.branch);
+        let only_leaf = data
+            .iter()
+            .next()
+            .filter(|child| data.len() == 1 && matches!(***child, Node::Leaf { .. }))
+            .cloned();
+        self.focus = if !frame.edited {
+            Some(frame.branch)
+        } else if data.is_empty() {
+            None
+        } else {
+            only_leaf.or(Some(frame.branch))
+        };
+        self.edited = frame.edited;
+        self.position = 0;
+    }
+
+    /// Writes an edited focus into the innermost branch, which is copied
+    /// first if it is still shared with other maps.
+    fn flush(&mut self) {
+        if !self.edited {
+            return;
+        }
+        self.edited = false;
+        let frame = self.stack.last_mut().unwrap();
+        frame.edited = true;
+        let Node::Branch { data, weight } = Arc::make_mut(&mut frame.branch) else {
+            unreachable!("frames only hold branches");
+        };
+        match &self.focus {
+            Some(node) if node.weight() > 0 => data.insert(frame.index, Arc::clone(node)),
+            _ => drop(data.remove(frame.index)),
+        }
+        *weight = data.iter().map(|child| child.weight()).sum();
+    }
+}
+
+impl<K: Eq, V, S, const B: usize> Cursor<K, V, S, B> {
+    /// Moves to `key` and returns whether it is in the map. When it is not,
+    /// the cursor stops at the entry that would follow it.
+    pub fn seek<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+        S: BuildHasher,
+    {
+        let hash = self.hasher.hash_one(key);
+        match self.locate(hash, |k| k.borrow() == key) {
+            Spot::Found => true,
+            Spot::Vacant | Spot::Beside => {
+                self.settle_after(hash);
+                false
+            }
+        }
+    }
+
+    /// Inserts the entry and moves to it.
+    pub fn insert(&mut self, key: K, value: V)
+    where
+        K: Hash,
+        S: BuildHasher,
+    {
+        let hash = self.hasher.hash_one(&key);
+        let spot = self.locate(hash, |k| *k == key);
+        let entry = Arc::new((key, value));
+        let focus = match self.focus.as_ref() {
+            None => {
+                self.position = 0;
+                Arc::new(Node::leaf(hash, smallvec![entry]))
+            }
+            Some(node) => match &**node {
+                Node::Leaf {
+                    hash: leaf_hash, ..
+                } if *leaf_hash != hash => {
+                    let leaf = Arc::new(Node::leaf(hash, smallvec![entry]));
+                    let shift = self.stack.len() * BitShifter::<B>::BITS;
+                    Node::merge(node, &leaf, &TakeRight, shift)
+                }
+                Node::Leaf { data, .. } => {
+                    let mut data = data.clone();
+                    if matches!(spot, Spot::Found) {
+                        data[self.position] = entry;
+                    } else {
+                        self.position = data.len();
+                        data.push(entry);
+                    }
+                    Arc::new(Node::leaf(hash, data))
+                }
+                Node::Branch { .. } => unreachable!("keys are only located in leaves"),
+            },
+        };
+        let split = matches!(*focus, Node::Branch { .. });
+        self.focus = Some(focus);
+        self.edited = true;
+        if split {
+            // The new entry is alone in its leaf, so nothing needs to match.
+            self.locate(hash, |_| false);
+            self.position = 0;
+        }
+    }
+
+    /// Removes `key` and moves to the entry that followed it. Returns the
+    /// removed entry, if there was one.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<Arc<(K, V)>>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+        S: BuildHasher,
+    {
+        let hash = self.hasher.hash_one(key);
+        match self.locate(hash, |k| k.borrow() == key) {
+            Spot::Found => self.remove_current(),
+            Spot::Vacant | Spot::Beside => {
+                self.settle_after(hash);
+                None
+            }
+        }
+    }
+
+    /// Climbs to the deepest branch on the way to `hash` and descends from
+    /// there to the slot where an entry with that hash belongs.
+    fn locate(&mut self, hash: u64, mut is_match: impl FnMut(&K) -> bool) -> Spot {
+        let diverged = self
+            .stack
+            .iter()
+            .enumerate()
+            .position(|(depth, frame)| frame.index != chunk::<B>(hash, depth));
+        if let Some(depth) = diverged {
+            while self.stack.len() > depth + 1 {
+                self.pop();
+            }
+            self.enter(chunk::<B>(hash, depth));
+        }
+        loop {
+            match self.focus.as_deref() {
+                None => return Spot::Vacant,
+                Some(Node::Branch { .. }) => {
+                    self.descend();
+                    self.enter(chunk::<B>(hash, self.stack.len() - 1));
+                }
+                Some(Node::Leaf {
+                    hash: leaf_hash,
+                    data,
+                    ..
+                }) => {
+                    if *leaf_hash == hash {
+                        if let Some(position) = data.iter().position(|entry| is_match(&entry.0)) {
+                            self.position = position;
+                            return Spot::Found;
+                        }
+                    }
+                    return Spot::Beside;
+                }
+            }
+        }
+    }
+
+    /// Moves from the slot where a missing entry with `hash` would go to the
+    /// entry that would follow it.
+    fn settle_after(&mut self, hash: u64) {
+        // A leaf with the same hash keeps new entries at its end, otherwise
+        // the first slot where the hashes part decides the order.
+        if let Some(Node::Leaf {
+            hash: leaf_hash, ..
+        }) = self.focus.as_deref()
+        {
+            if *leaf_hash != hash {
+                let depth = (self.stack.len()..64)
+                    .find(|depth| chunk::<B>(hash, *depth) != chunk::<B>(*leaf_hash, *depth))
+                    .expect("leaves with distinct hashes always diverge");
+                if chunk::<B>(hash, depth) < chunk::<B>(*leaf_hash, depth) {
+                    self.position = 0;
+                    return;
+                }
+            }
+        }
+        self.first_from(self.index() + 1);
+    }
+}
+
+/// Yields the entry under the cursor and moves past it.
+impl<K, V, S, const B: usize> Iterator for Cursor<K, V, S, B> {
+    type Item = Arc<(K, V)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Some(Node::Leaf { data, .. }) = self.focus.as_deref() else {
+            return None;
+        };
+        let entry = Arc::clone(data.get(self.position)?);
+        self.advance();
+        Some(entry)
+    }
+}
+
+fn children<K, V, const B: usize>(node: &Node<K, V, B>) -> &Children<K, V, B> {
+    match node {
+        Node::Branch { data, .. } => data,
+        Node::Leaf { .. } => unreachable!("frames only hold branches"),
+    }
+}
+
+/// Slot taken at `depth` by an entry with `hash`.
+fn chunk<const B: usize>(hash: u64, depth: usize) -> usize {
+    let shift = u32::try_from(depth * BitShifter::<B>::BITS).unwrap_or(u32::MAX);
+    (hash.checked_shr(shift).unwrap_or(0) & (B as u64 - 1)) as usize
+}
@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use crate::{iter::Iter, nodes::Node};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DiffItem<'a, K, V> {
+    Added(&'a K, &'a V),
+    Removed(&'a K, &'a V),
+    Changed { key: &'a K, old: &'a V, new: &'a V },
+}
+
+enum Pending<'a, K, V, const B: usize> {
+    Both(&'a Arc<Node<K, V, B>>, &'a Arc<Node<K, V, B>>),
+    Added(&'a Node<K, V, B>),
+    Removed(&'a Node<K, V, B>),
+}
+
+pub struct Diff<'a, K, V, const B: usize = 16> {
+    pending: Vec<Pending<'a, K, V, B>>,
+    subtree: Option<(bool, Iter<'a, K, V, B>)>,
+    buffer: Vec<DiffItem<'a, K, V>>,
+}
+
+impl<'a, K, V, const B: usize> Diff<'a, K, V, B> {
+    pub(crate) fn new(old: &'a Arc<Node<K, V, B>>, new: &'a Arc<Node<K, V, B>>) -> Self {
+        Diff {
+            pending: vec![Pending::Both(old, new)],
+            subtree: None,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl<'a, K: Eq, V: PartialEq, const B: usize> Diff<'a, K, V, B> {
+    fn compare<I, J>(&mut self, old: &I, new: &J)
+    where
+        I: Iterator<Item = &'a Arc<(K, V)>> + Clone,
+        J: Iterator<Item = &'a Arc<(K, V)>> + Clone,
+    {
+        for o in old.clone() {
+            match new.clone().find(|n| n.0 == o.0) {
+                None => self.buffer.push(DiffItem::Removed(&o.0, &o.1)),
+                Some(n) if n.1 != o.1 => self.buffer.push(DiffItem::Changed {
+                    key: &o.0,
+                    old: &o.1,
+                    new: &n.1,
+                }),
+                Some(_) => {}
+            }
+        }
+        for n in new.clone() {
+            if old.clone().all(|o| o.0 != n.0) {
+                self.buffer.push(DiffItem::Added(&n.0, &n.1));
+            }
+        }
+    }
+}
+
+impl<'a, K: Eq, V: PartialEq, const B: usize> Iterator for Diff<'a, K, V, B> {
+    type Item = DiffItem<'a, K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.buffer.pop() {
+                return Some(item);
+            }
+
+            if let Some((added, iter)) = &mut self.subtree {
+                match iter.next() {
+                    Some(entry) if *added => return Some(DiffItem::Added(&entry.0, &entry.1)),
+                    Some(entry) => return Some(DiffItem::Removed(&entry.0, &entry.1)),
+                    None => self.subtree = None,
+                }
+            }
+
+            match self.pending.pop()? {
+                Pending::Added(node) => self.subtree = Some((true, Iter::from_node(node))),
+                Pending::Removed(node) => self.subtree = Some((false, Iter::from_node(node))),
+                Pending::Both(old, new) if Arc::ptr_eq(old, new) => {}
+                Pending::Both(old, new) => match (&**old, &**new) {
+                    (
+                        Node::Leaf {
+                            hash: old_hash,
+                            data: old_data,
+                            ..
+                        },
+                        Node::Leaf {
+                            hash: new_hash,
+                            data: new_data,
+                            ..
+                        },
+                    ) if old_hash == new_hash => self.compare(&old_data.iter(), &new_data.iter()),
+                    (Node::Branch { data: old_data, .. }, Node::Branch { data: new_data, .. }) => {
+                        for k in (0..B).rev() {
+                            match (old_data.get(k), new_data.get(k)) {
+                                (Some(o), Some(n)) => self.pending.push(Pending::Both(o, n)),
+                                (Some(o), None) => self.pending.push(Pending::Removed(o)),
+                                (None, Some(n)) => self.pending.push(Pending::Added(n)),
+                                (None, None) => {}
+                            }
+                        }
+                    }
+                    _ => {
+                        let old_entries = Iter::from_node(old).collect::<Vec<_>>();
+                        let new_entries = Iter::from_node(new).collect::<Vec<_>>();
+                        self.compare(&old_entries.into_iter(), &new_entries.into_iter());
+                    }
+                },
+            }
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Write},
+    sync::Arc,
+};
+
+use rustc_hash::FxBuildHasher;
+
+use crate::{nodes::Node, PerMap, PerSet};
+
+struct Vertex {
+    label: String,
+    leaf: bool,
+    map: usize,
+    shared: bool,
+}
+
+/// Renders any number of maps as a single Graphviz graph. Nodes are
+/// identified by address, so a subtree shared between maps becomes one vertex
+/// with several incoming edges. Vertices reachable from more than one map are
+/// filled, which leaves the copied paths unfilled.
+#[derive(Default)]
+pub struct DotWriter {
+    vertices: Vec<Vertex>,
+    ids: HashMap<*const (), usize, FxBuildHasher>,
+    edges: Vec<(usize, usize, usize)>,
+    roots: Vec<usize>,
+}
+
+impl DotWriter {
+    #[must_use]
+    pub fn new() -> Self {
+        DotWriter::default()
+    }
+
+    pub fn add<K: Debug, V: Debug, S, const B: usize>(&mut self, map: &PerMap<K, V, S, B>) {
+        let root = self.visit(&map.data);
+        self.roots.push(root);
+    }
+
+    pub fn add_set<K: Debug, S, const B: usize>(&mut self, set: &PerSet<K, S, B>) {
+        self.add(&set.0);
+    }
+
+    #[must_use]
+    pub fn finish(self) -> String {
+        let mut out = String::from("digraph {\n  node [fontname=\"monospace\"];\n");
+        for (map, root) in self.roots.iter().enumerate() {
+            writeln!(out, "  m{map} [shape=plaintext, label=\"#{map}\"];").unwrap();
+            writeln!(out, "  m{map} -> n{root};").unwrap();
+        }
+        for (id, vertex) in self.vertices.iter().enumerate() {
+            let shape = if vertex.leaf { "box" } else { "circle" };
+            let style = if vertex.shared { ", style=filled" } else { "" };
+            writeln!(
+                out,
+                "  n{id} [shape={shape}, label=\"{}\"{style}];",
+                vertex.label
+            )
+            .unwrap();
+        }
+        for (from, to, index) in self.edges {
+            writeln!(out, "  n{from} -> n{to} [label=\"{index:x}\"];").unwrap();
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    fn visit<K: Debug, V: Debug, const B: usize>(&mut self, node: &Arc<Node<K, V, B>>) -> usize {
+        let maps = self.roots.len();
+        if let Some(&id) = self.ids.get(&Arc::as_ptr(node).cast()) {
+            let vertex = &mut self.vertices[id];
+            if vertex.map != maps && !vertex.shared {
+                vertex.map = maps;
+                vertex.shared = true;
+                if let Node::Branch { data, .. } = &**node {
+                    for child in data {
+                        self.visit(child);
+                    }
+                }
+            }
+            return id;
+        }
+        let id = self.vertices.len();
+        self.ids.insert(Arc::as_ptr(node).cast(), id);
+        self.vertices.push(Vertex {
+            label: String::new(),
+            leaf: false,
+            map: maps,
+            shared: false,
+        });
+        match &**node {
+            Node::Leaf { hash, data, .. } => {
+                let mut label = format!("{hash:016x}");
+                for entry in data {
+                    label.push_str(&escape(&format!("\n{:?}: {:?}", entry.0, entry.1)));
+                }
+                self.vertices[id].label = label;
+                self.vertices[id].leaf = true;
+            }
+            Node::Branch { data, weight } => {
+                self.vertices[id].label = weight.to_string();
+                for index in data.keys() {
+                    let child = self.visit(data.get(index).unwrap());
+                    self.edges.push((id, child, index));
+                }
+            }
+        }
+        id
+    }
+}
+
+pub fn render<'a, K, V, S, I, const B: usize>(maps: I) -> String
+where
+    K: Debug + 'a,
+    V: Debug + 'a,
+    S: 'a,
+    I: IntoIterator<Item = &'a PerMap<K, V, S, B>>,
+{
+    let mut writer = DotWriter::new();
+    for map in maps {
+        writer.add(map);
+    }
+    writer.finish()
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,132 @@
+use std::{
+    hash::{BuildHasher, Hash},
+    sync::Arc,
+};
+
+use crate::{
+    nodes::{BitShifter, Found, Missing, Node, Path, Target},
+    PerMap,
+};
+
+pub enum Entry<'a, K, V, S, const B: usize = 16> {
+    Occupied(OccupiedEntry<'a, K, V, S, B>),
+    Vacant(VacantEntry<'a, K, V, S, B>),
+}
+
+pub struct OccupiedEntry<'a, K, V, S, const B: usize = 16> {
+    map: &'a PerMap<K, V, S, B>,
+    key: K,
+    path: Path<'a, K, V, B>,
+    target: Found<'a, K, V, B>,
+}
+
+pub struct VacantEntry<'a, K, V, S, const B: usize = 16> {
+    map: &'a PerMap<K, V, S, B>,
+    key: K,
+    path: Path<'a, K, V, B>,
+    target: Missing<'a, K, V, B>,
+}
+
+impl<K, V, S, const B: usize> PerMap<K, V, S, B>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    pub fn entry(&self, key: K) -> Entry<'_, K, V, S, B> {
+        let hash = self.hasher.hash_one(&key);
+        let address = BitShifter::new(hash);
+        match Node::find(&self.data, address, |k| *k == key) {
+            (path, Target::Found(target)) => Entry::Occupied(OccupiedEntry {
+                map: self,
+                key,
+                path,
+                target,
+            }),
+            (path, Target::Missing(target)) => Entry::Vacant(VacantEntry {
+                map: self,
+                key,
+                path,
+                target,
+            }),
+        }
+    }
+}
+
+impl<K: Eq, V, S: Clone, const B: usize> Entry<'_, K, V, S, B> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    #[must_use]
+    pub fn insert(self, value: V) -> PerMap<K, V, S, B> {
+        match self {
+            Entry::Occupied(entry) => entry.insert(value),
+            Entry::Vacant(entry) => entry.insert(value),
+        }
+    }
+
+    #[must_use]
+    pub fn or_insert(self, default: V) -> PerMap<K, V, S, B> {
+        self.or_insert_with(|| default)
+    }
+
+    #[must_use]
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> PerMap<K, V, S, B> {
+        match self {
+            Entry::Occupied(entry) => entry.map.with_data(Arc::clone(&entry.map.data)),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    #[must_use]
+    pub fn and_modify<F: FnOnce(&V) -> V>(self, f: F) -> PerMap<K, V, S, B> {
+        match self {
+            Entry::Occupied(entry) => {
+                let value = f(entry.get());
+                entry.insert(value)
+            }
+            Entry::Vacant(entry) => entry.map.with_data(Arc::clone(&entry.map.data)),
+        }
+    }
+}
+
+impl<'a, K, V, S: Clone, const B: usize> OccupiedEntry<'a, K, V, S, B> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn get(&self) -> &'a V {
+        &self.target.get().1
+    }
+
+    #[must_use]
+    pub fn insert(self, value: V) -> PerMap<K, V, S, B> {
+        let node = self.target.replace(self.key, value);
+        self.map.with_data(self.path.rebuild(node))
+    }
+
+    #[must_use]
+    pub fn remove(self) -> PerMap<K, V, S, B> {
+        let node = self.target.remove();
+        self.map.with_data(self.path.rebuild(node))
+    }
+}
+
+impl<K: Eq, V, S: Clone, const B: usize> VacantEntry<'_, K, V, S, B> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    #[must_use]
+    pub fn insert(self, value: V) -> PerMap<K, V, S, B> {
+        let node = self.target.insert(self.key, value);
+        self.map.with_data(self.path.rebuild(node))
+    }
+}
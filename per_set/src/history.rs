@@ -0,0 +1,177 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::{Arc, Weak},
+};
+
+use rustc_hash::FxBuildHasher;
+
+use crate::{
+    nodes::Node,
+    stats::{Stats, StatsCollector},
+    PerMap,
+};
+
+/// Versions of a map, each tagged with the generation it was recorded in.
+/// Generations only grow, so dropping old versions never renumbers the ones
+/// that are kept.
+pub struct PerMapHistory<K, V, S = FxBuildHasher, const B: usize = 16> {
+    versions: VecDeque<(u64, PerMap<K, V, S, B>)>,
+    next: u64,
+}
+
+/// What dropping old versions gave back. Nodes and entries still reachable
+/// from the remaining versions or from any other map are not counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Released {
+    pub versions: usize,
+    pub nodes: usize,
+    pub entries: usize,
+}
+
+impl<K, V, S, const B: usize> Default for PerMapHistory<K, V, S, B> {
+    fn default() -> Self {
+        PerMapHistory {
+            versions: VecDeque::new(),
+            next: 0,
+        }
+    }
+}
+
+impl<K, V, S, const B: usize> PerMapHistory<K, V, S, B> {
+    #[must_use]
+    pub fn new() -> Self {
+        PerMapHistory::default()
+    }
+
+    /// Stores `map` as the newest version and returns its generation.
+    pub fn record(&mut self, map: PerMap<K, V, S, B>) -> u64 {
+        let generation = self.next;
+        self.versions.push_back((generation, map));
+        self.next += 1;
+        generation
+    }
+
+    #[must_use]
+    pub fn get(&self, generation: u64) -> Option<&PerMap<K, V, S, B>> {
+        let index = self
+            .versions
+            .binary_search_by_key(&generation, |(generation, _)| *generation)
+            .ok()?;
+        Some(&self.versions[index].1)
+    }
+
+    #[must_use]
+    pub fn latest(&self) -> Option<(u64, &PerMap<K, V, S, B>)> {
+        self.versions
+            .back()
+            .map(|(generation, map)| (*generation, map))
+    }
+
+    #[must_use]
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (u64, &PerMap<K, V, S, B>)> {
+        self.versions
+            .iter()
+            .map(|(generation, map)| (*generation, map))
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.versions.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.versions.is_empty()
+    }
+
+    /// Measures all the kept versions together.
+    #[must_use]
+    pub fn stats(&self) -> Stats {
+        let mut collector = StatsCollector::new();
+        for (_, map) in &self.versions {
+            collector.add(map);
+        }
+        collector.finish()
+    }
+
+    /// Drops every version recorded before `generation`.
+    pub fn drop_older_than(&mut self, generation: u64) -> Released {
+        let count = self.versions.partition_point(|(g, _)| *g < generation);
+        self.release(count)
+    }
+
+    /// Drops all but the `count` newest versions.
+    pub fn keep_latest(&mut self, count: usize) -> Released {
+        self.release(self.versions.len().saturating_sub(count))
+    }
+
+    /// Before the oldest versions are dropped, everything they reach and the
+    /// oldest kept version does not is remembered through weak references.
+    /// Whatever is dead once the versions are gone was freed with them.
+    fn release(&mut self, count: usize) -> Released {
+        let dropped = self.versions.drain(..count).collect::<Vec<_>>();
+        let mut sweep = Sweep::default();
+        for (_, map) in &dropped {
+            let kept = self.versions.front().map(|(_, kept)| &kept.data);
+            sweep.visit(&map.data, kept);
+        }
+        drop(dropped);
+        Released {
+            versions: count,
+            nodes: sweep.nodes.iter().filter(|n| n.strong_count() == 0).count(),
+            entries: sweep
+                .entries
+                .iter()
+                .filter(|e| e.strong_count() == 0)
+                .count(),
+        }
+    }
+}
+
+struct Sweep<K, V, const B: usize> {
+    seen: HashSet<*const (), FxBuildHasher>,
+    nodes: Vec<Weak<Node<K, V, B>>>,
+    entries: Vec<Weak<(K, V)>>,
+}
+
+impl<K, V, const B: usize> Default for Sweep<K, V, B> {
+    fn default() -> Self {
+        Sweep {
+            seen: HashSet::default(),
+            nodes: Vec::new(),
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<K, V, const B: usize> Sweep<K, V, B> {
+    /// Walks `node` next to the node found at the same position in the oldest
+    /// kept version. Subtrees the two have in common stay alive, so they are
+    /// skipped without being walked.
+    fn visit(&mut self, node: &Arc<Node<K, V, B>>, kept: Option<&Arc<Node<K, V, B>>>) {
+        if kept.is_some_and(|kept| Arc::ptr_eq(node, kept))
+            || !self.seen.insert(Arc::as_ptr(node).cast())
+        {
+            return;
+        }
+        self.nodes.push(Arc::downgrade(node));
+        match &**node {
+            Node::Leaf { data, .. } => {
+                for entry in data {
+                    if self.seen.insert(Arc::as_ptr(entry).cast()) {
+                        self.entries.push(Arc::downgrade(entry));
+                    }
+                }
+            }
+            Node::Branch { data, .. } => {
+                for k in data.keys() {
+                    let kept = match kept.map(|kept| &**kept) {
+                        Some(Node::Branch { data, .. }) => data.get(k),
+                        _ => None,
+                    };
+                    self.visit(data.get(k).unwrap(), kept);
+                }
+            }
+        }
+    }
+}
@@ -1,36 +1,42 @@
 use core::slice;
 use std::sync::Arc;
 
+use smallvec::SmallVec;
+
 use crate::{nodes::Node, PerMap};
 
-pub struct Iter<'a, K, V> {
-    stack: Vec<sparse_vec::Iter<'a, 16, Arc<Node<K, V>>>>,
+pub struct Iter<'a, K, V, const B: usize = 16> {
+    stack: Vec<sparse_vec::Iter<'a, B, Arc<Node<K, V, B>>>>,
     leaves: Option<slice::Iter<'a, Arc<(K, V)>>>,
+    remaining: usize,
 }
 
-impl<'a, K, V> Iter<'a, K, V> {
-    pub fn new<S>(map: &'a PerMap<K, V, S>) -> Self {
-        let bottom = match map.data.as_ref() {
-            Node::Branch { data, .. } => data.iter(),
-            Node::Leaf { .. } => unreachable!(),
-        };
+impl<'a, K, V, const B: usize> Iter<'a, K, V, B> {
+    pub fn new<S>(map: &'a PerMap<K, V, S, B>) -> Self {
+        Iter::from_node(&map.data)
+    }
 
-        Iter {
-            stack: vec![bottom],
-            leaves: None,
+    pub(crate) fn from_node(node: &'a Node<K, V, B>) -> Self {
+        match node {
+            Node::Branch { data, weight } => Iter {
+                stack: vec![data.iter()],
+                leaves: None,
+                remaining: *weight,
+            },
+            Node::Leaf { data, weight, .. } => Iter {
+                stack: Vec::new(),
+                leaves: Some(data.iter()),
+                remaining: *weight,
+            },
         }
     }
-}
-
-impl<'a, K, V> Iterator for Iter<'a, K, V> {
-    type Item = &'a Arc<(K, V)>;
 
-    fn next(&mut self) -> Option<Self::Item> {
+    fn next_leaf(&mut self) -> Option<&'a Arc<(K, V)>> {
         match self.leaves.as_mut().and_then(Iterator::next) {
             Some(leaf) => Some(leaf),
             None => loop {
                 let top = self.stack.last_mut()?;
-                let step: Step<'a, K, V> = match top.next() {
+                let step: Step<'a, K, V, B> = match top.next() {
                     None => Step::Pop,
                     Some(next) => match next.as_ref() {
                         Node::Leaf { data, .. } => Step::Ret(data.iter()),
@@ -56,8 +62,102 @@ impl<'a, K, V> Iterator for Iter<'a, K, V> {
     }
 }
 
-enum Step<'a, K, V> {
+impl<'a, K, V, const B: usize> Iterator for Iter<'a, K, V, B> {
+    type Item = &'a Arc<(K, V)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let res = self.next_leaf();
+        if res.is_some() {
+            self.remaining -= 1;
+        }
+        res
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V, const B: usize> ExactSizeIterator for Iter<'_, K, V, B> {}
+
+enum Step<'a, K, V, const B: usize> {
     Pop,
-    Push(sparse_vec::Iter<'a, 16, Arc<Node<K, V>>>),
+    Push(sparse_vec::Iter<'a, B, Arc<Node<K, V, B>>>),
     Ret(slice::Iter<'a, Arc<(K, V)>>),
 }
+
+pub struct Keys<'a, K, V, const B: usize = 16>(pub(crate) Iter<'a, K, V, B>);
+
+impl<'a, K, V, const B: usize> Iterator for Keys<'a, K, V, B> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|entry| &entry.0)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K, V, const B: usize> ExactSizeIterator for Keys<'_, K, V, B> {}
+
+pub struct Values<'a, K, V, const B: usize = 16>(pub(crate) Iter<'a, K, V, B>);
+
+impl<'a, K, V, const B: usize> Iterator for Values<'a, K, V, B> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|entry| &entry.1)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K, V, const B: usize> ExactSizeIterator for Values<'_, K, V, B> {}
+
+pub struct IntoIter<K, V, const B: usize = 16> {
+    stack: Vec<Arc<Node<K, V, B>>>,
+    leaves: smallvec::IntoIter<[Arc<(K, V)>; 2]>,
+    remaining: usize,
+}
+
+impl<K, V, const B: usize> IntoIter<K, V, B> {
+    pub fn new<S>(map: PerMap<K, V, S, B>) -> Self {
+        IntoIter {
+            remaining: map.len(),
+            stack: vec![map.data],
+            leaves: SmallVec::new().into_iter(),
+        }
+    }
+}
+
+impl<K: Clone, V: Clone, const B: usize> Iterator for IntoIter<K, V, B> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(leaf) = self.leaves.next() {
+                self.remaining -= 1;
+                return Some(Arc::unwrap_or_clone(leaf));
+            }
+
+            match Arc::try_unwrap(self.stack.pop()?) {
+                Ok(Node::Leaf { data, .. }) => self.leaves = data.into_iter(),
+                Ok(Node::Branch { data, .. }) => self.stack.extend(data),
+                Err(shared) => match shared.as_ref() {
+                    Node::Leaf { data, .. } => self.leaves = data.clone().into_iter(),
+                    Node::Branch { data, .. } => self.stack.extend(data.iter().cloned()),
+                },
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: Clone, V: Clone, const B: usize> ExactSizeIterator for IntoIter<K, V, B> {}
@@ -1,10 +1,39 @@
+mod bag;
+mod bimap;
+mod builder;
+pub mod cursor;
+pub mod diff;
+pub mod dot;
+pub mod entry;
+pub mod history;
 pub mod iter;
+mod multimap;
 mod nodes;
+pub mod ord_map;
+mod ord_nodes;
+mod ord_set;
+#[cfg(feature = "rayon")]
+pub mod par;
+pub mod raw_entry;
+#[cfg(feature = "serde")]
+mod serde_impl;
 mod set_wrapper;
+pub mod snapshot;
+pub mod stats;
 mod structure;
+pub mod vector;
+mod vector_nodes;
 
 #[cfg(test)]
 mod tests;
 
-pub use set_wrapper::PerSet;
+pub use bag::{Iter as BagIter, PerBag};
+pub use bimap::{BiMapError, Iter as BiMapIter, PerBiMap};
+pub use builder::PerMapBuilder;
+pub use history::PerMapHistory;
+pub use multimap::{Iter as MultiMapIter, PerMultiMap};
+pub use ord_map::PerOrdMap;
+pub use ord_set::{Iter as OrdSetIter, PerOrdSet};
+pub use set_wrapper::{Element, IntoIter as SetIntoIter, Iter as SetIter, PerSet};
 pub use structure::PerMap;
+pub use vector::PerVec;
@@ -0,0 +1,259 @@
+use std::{
+    borrow::Borrow,
+    cell::Cell,
+    hash::{BuildHasher, Hash, Hasher},
+    sync::Arc,
+};
+
+use rustc_hash::FxBuildHasher;
+
+use crate::{
+    entry::Entry,
+    iter,
+    nodes::{Node, Resolve},
+    PerMap, PerSet, SetIter,
+};
+
+pub struct PerMultiMap<K, V, S = FxBuildHasher> {
+    data: PerMap<K, PerSet<V, S>, S>,
+    len: usize,
+}
+
+impl<K, V> PerMultiMap<K, V> {
+    #[must_use]
+    pub fn empty() -> Self {
+        Self::with_hasher(FxBuildHasher)
+    }
+}
+
+impl<K, V> Default for PerMultiMap<K, V> {
+    fn default() -> Self {
+        PerMultiMap::<K, V>::empty()
+    }
+}
+
+impl<K, V, S> PerMultiMap<K, V, S> {
+    #[must_use]
+    pub fn with_hasher(hash_builder: S) -> Self {
+        PerMultiMap {
+            data: PerMap::with_hasher(hash_builder),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn keys(&self) -> iter::Keys<'_, K, PerSet<V, S>> {
+        self.data.keys()
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V, S> {
+        Iter {
+            keys: self.data.iter(),
+            values: None,
+        }
+    }
+}
+
+impl<K, V, S> PerMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    #[must_use]
+    pub fn insert(&self, key: K, value: V) -> Self {
+        let data = match self.data.entry(key) {
+            Entry::Occupied(entry) => {
+                let values = entry.get().insert(value);
+                if values.len() == entry.get().len() {
+                    return self.clone();
+                }
+                entry.insert(values)
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(PerSet::with_hasher(self.data.hasher.clone()).insert(value))
+            }
+        };
+        PerMultiMap {
+            data,
+            len: self.len + 1,
+        }
+    }
+
+    #[must_use]
+    pub fn remove<Q, R>(&self, key: &Q, value: &R) -> Self
+    where
+        K: Borrow<Q> + Clone,
+        Q: Eq + Hash + ?Sized,
+        V: Borrow<R>,
+        R: Eq + Hash + ?Sized,
+    {
+        let mut removed = false;
+        let data = self.data.modify(key, |values| {
+            let left = values.remove(value);
+            removed = left.len() < values.len();
+            removed.then(|| (!left.is_empty()).then_some(left))
+        });
+        PerMultiMap {
+            data,
+            len: self.len - usize::from(removed),
+        }
+    }
+
+    #[must_use]
+    pub fn remove_all<Q>(&self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        PerMultiMap {
+            data: self.data.remove(key),
+            len: self.len - self.get(key).map_or(0, PerSet::len),
+        }
+    }
+
+    #[must_use]
+    pub fn get<Q>(&self, key: &Q) -> Option<&PerSet<V, S>>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.data.get(key)
+    }
+
+    #[must_use]
+    pub fn contains<Q, R>(&self, key: &Q, value: &R) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+        V: Borrow<R>,
+        R: Eq + Hash + ?Sized,
+    {
+        self.get(key).is_some_and(|values| values.contains(value))
+    }
+
+    #[must_use]
+    pub fn union(&self, other: &PerMultiMap<K, V, S>) -> Self
+    where
+        K: Clone,
+    {
+        let resolve = UnionValues(Cell::new(0));
+        let data = Node::merge(&self.data.data, &other.data.data, &resolve, 0);
+        PerMultiMap {
+            data: self.data.with_data(data),
+            len: self.len + other.len - resolve.0.get(),
+        }
+    }
+}
+
+/// Unions the value sets of keys found in both maps and counts how many
+/// values the two sides have in common, so that the length of the union is
+/// known without walking it. Shared subtrees are reused and only their sets'
+/// lengths are added up.
+struct UnionValues(Cell<usize>);
+
+impl<K, V, S> Resolve<K, PerSet<V, S>> for UnionValues
+where
+    K: Clone,
+    V: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    const IDEMPOTENT: bool = true;
+
+    fn resolve(
+        &self,
+        left: &Arc<(K, PerSet<V, S>)>,
+        right: &Arc<(K, PerSet<V, S>)>,
+    ) -> Arc<(K, PerSet<V, S>)> {
+        if Arc::ptr_eq(left, right) {
+            self.0.set(self.0.get() + left.1.len());
+            return Arc::clone(left);
+        }
+        let values = left.1.union(&right.1);
+        self.0
+            .set(self.0.get() + left.1.len() + right.1.len() - values.len());
+        Arc::new((left.0.clone(), values))
+    }
+
+    fn shared<const B: usize>(&self, node: &Node<K, PerSet<V, S>, B>) {
+        let common = iter::Iter::from_node(node)
+            .map(|entry| entry.1.len())
+            .sum::<usize>();
+        self.0.set(self.0.get() + common);
+    }
+}
+
+impl<K, V, S: Clone> Clone for PerMultiMap<K, V, S> {
+    fn clone(&self) -> Self {
+        PerMultiMap {
+            data: self.data.with_data(Arc::clone(&self.data.data)),
+            len: self.len,
+        }
+    }
+}
+
+impl<K: Eq, V: Eq, S> PartialEq for PerMultiMap<K, V, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.data == other.data
+    }
+}
+
+impl<K: Eq, V: Eq, S> Eq for PerMultiMap<K, V, S> {}
+
+impl<K: Hash, V: Hash, S: BuildHasher> Hash for PerMultiMap<K, V, S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.data.hash(state);
+    }
+}
+
+impl<K, V, S> FromIterator<(K, V)> for PerMultiMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: Eq + Hash,
+    S: BuildHasher + Clone + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        iter.into_iter().fold(
+            PerMultiMap::with_hasher(S::default()),
+            |map, (key, value)| map.insert(key, value),
+        )
+    }
+}
+
+pub struct Iter<'a, K, V, S> {
+    keys: iter::Iter<'a, K, PerSet<V, S>>,
+    values: Option<(&'a K, SetIter<'a, V>)>,
+}
+
+impl<'a, K, V, S> Iterator for Iter<'a, K, V, S> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((key, values)) = &mut self.values {
+                if let Some(value) = values.next() {
+                    return Some((*key, &value.0 .0));
+                }
+            }
+            let entry = self.keys.next()?;
+            self.values = Some((&entry.0, entry.1.iter()));
+        }
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a PerMultiMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+
+    type IntoIter = Iter<'a, K, V, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
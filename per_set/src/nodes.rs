@@ -1,25 +1,31 @@
 use std::{
-    borrow::Borrow,
     fmt::{self, Debug, Formatter},
-    hash::Hash,
     sync::Arc,
 };
 
 use smallvec::{smallvec, SmallVec};
 use sparse_vec::SparseVec;
 
-pub enum Node<K, V> {
+pub type Entries<K, V> = SmallVec<[Arc<(K, V)>; 2]>;
+
+pub type Children<K, V, const B: usize> = SparseVec<B, Arc<Node<K, V, B>>>;
+
+pub enum Node<K, V, const B: usize = 16> {
     Leaf {
-        data: SmallVec<[Arc<(K, V)>; 2]>,
+        hash: u64,
+        data: Entries<K, V>,
         weight: usize,
     },
     Branch {
-        data: SparseVec<16, Arc<Node<K, V>>>,
+        data: Children<K, V, B>,
         weight: usize,
     },
 }
 
-impl<K, V> Node<K, V> {
+impl<K, V, const B: usize> Node<K, V, B> {
+    /// Hash bits consumed by every level of the trie.
+    pub const BITS: usize = BitShifter::<B>::BITS;
+
     pub fn empty_branch() -> Self {
         Node::Branch {
             data: SparseVec::new(),
@@ -27,24 +33,58 @@ impl<K, V> Node<K, V> {
         }
     }
 
-    fn allocate(key: K, value: V, address: BitShifter) -> Self {
-        match address.shift() {
-            None => Node::Leaf {
-                data: smallvec![Arc::new((key, value))],
-                weight: 1,
-            },
-            Some((new_address, index)) => {
-                let mut data = SparseVec::new();
-                data.insert(
-                    index as usize,
-                    Arc::new(Node::allocate(key, value, new_address)),
-                );
+    fn allocate(key: K, value: V, hash: u64) -> Self {
+        Node::leaf(hash, smallvec![Arc::new((key, value))])
+    }
 
-                Node::Branch {
-                    data: data.clone(),
-                    weight: 1,
-                }
+    pub fn leaf(hash: u64, data: Entries<K, V>) -> Self {
+        let weight = data.len();
+        Node::Leaf { hash, data, weight }
+    }
+
+    pub fn branch(data: Children<K, V, B>) -> Self {
+        let weight = data.iter().map(|node| node.weight()).sum();
+        Node::Branch { data, weight }
+    }
+
+    /// Below the root a branch that holds nothing but a single leaf is
+    /// replaced by that leaf, so every subtree has exactly one shape.
+    fn compact(data: Children<K, V, B>, shift: usize) -> Arc<Node<K, V, B>> {
+        if shift > 0 && data.len() == 1 {
+            let child = data.iter().next().unwrap();
+            if let Node::Leaf { .. } = **child {
+                return Arc::clone(child);
+            }
+        }
+        Arc::new(Node::branch(data))
+    }
+
+    fn lift(node: &Arc<Node<K, V, B>>, shift: usize) -> Arc<Node<K, V, B>> {
+        match &**node {
+            Node::Leaf { hash, .. } => {
+                let (_, index) = BitShifter::<B>::at(*hash, shift)
+                    .shift()
+                    .expect("leaves with distinct hashes always diverge before the last chunk");
+                let mut data = SparseVec::new();
+                data.insert(index as usize, Arc::clone(node));
+                Arc::new(Node::branch(data))
             }
+            Node::Branch { .. } => Arc::clone(node),
+        }
+    }
+
+    /// Nodes found at the same position can only be combined directly when
+    /// both are branches or both are leaves for the same hash. Otherwise the
+    /// leaves are pushed one level down so that both sides become branches.
+    fn align(
+        left: &Arc<Node<K, V, B>>,
+        right: &Arc<Node<K, V, B>>,
+        shift: usize,
+    ) -> Option<[Arc<Node<K, V, B>>; 2]> {
+        match (&**left, &**right) {
+            (Node::Leaf { hash: l, .. }, Node::Leaf { hash: r, .. }) if l == r => None,
+            (Node::Branch { .. }, Node::Branch { .. }) => None,
+            _ => Some([Node::lift(left, shift), Node::lift(right, shift)]),
         }
     }
 
@@ -55,71 +95,142 @@ impl<K, V> Node<K, V> {
     }
 }
 
-impl<K, V> Default for Node<K, V> {
+impl<K, V, const B: usize> Clone for Node<K, V, B> {
+    fn clone(&self) -> Self {
+        match self {
+            Node::Leaf { hash, data, weight } => Node::Leaf {
+                hash: *hash,
+                data: data.clone(),
+                weight: *weight,
+            },
+            Node::Branch { data, weight } => Node::Branch {
+                data: data.clone(),
+                weight: *weight,
+            },
+        }
+    }
+}
+
+impl<K, V, const B: usize> Default for Node<K, V, B> {
     fn default() -> Self {
         Node::empty_branch()
     }
 }
 
-impl<K: Eq, V> Node<K, V> {
-    pub fn insert(
-        node: &Arc<Node<K, V>>,
+impl<K: Eq, V: PartialEq, const B: usize> Node<K, V, B> {
+    pub fn equal(left: &Arc<Node<K, V, B>>, right: &Arc<Node<K, V, B>>) -> bool {
+        if Arc::ptr_eq(left, right) {
+            return true;
+        }
+        if left.weight() != right.weight() {
+            return false;
+        }
+        match (&**left, &**right) {
+            (
+                Node::Leaf {
+                    hash: left_hash,
+                    data: left_data,
+                    ..
+                },
+                Node::Leaf {
+                    hash: right_hash,
+                    data: right_data,
+                    ..
+                },
+            ) => {
+                left_hash == right_hash
+                    && left_data
+                        .iter()
+                        .all(|l| right_data.iter().any(|r| l.0 == r.0 && l.1 == r.1))
+            }
+            (
+                Node::Branch {
+                    data: left_data, ..
+                },
+                Node::Branch {
+                    data: right_data, ..
+                },
+            ) => {
+                left_data.keys() == right_data.keys()
+                    && left_data
+                        .iter()
+                        .zip(right_data)
+                        .all(|(l, r)| Node::equal(l, r))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<K: Eq, V, const B: usize> Node<K, V, B> {
+    pub fn insert_mut(
+        node: &mut Arc<Node<K, V, B>>,
         key: K,
         value: V,
-        address: BitShifter,
-    ) -> Arc<Node<K, V>> {
-        let res = match &**node {
-            Node::Leaf { data, weight } => match data.into_iter().position(|arc| arc.0 == key) {
-                None => {
-                    let mut new_data: SmallVec<_> = data.clone();
-                    new_data.push(Arc::new((key, value)));
-                    Arc::new(Node::Leaf {
-                        data: new_data,
-                        weight: weight + 1,
-                    })
-                }
-                Some(pos) => {
-                    let mut new_data: SmallVec<_> = data.clone();
-                    new_data[pos] = Arc::new((key, value));
-                    Arc::new(Node::Leaf {
-                        data: new_data,
-                        weight: *weight,
-                    })
+        address: BitShifter<B>,
+    ) -> bool {
+        if let Node::Leaf { hash, .. } = &**node {
+            if *hash != address.hash() {
+                let leaf = Arc::new(Node::allocate(key, value, address.hash()));
+                *node = Node::merge(node, &leaf, &TakeRight, address.offset());
+                return true;
+            }
+        }
+        match Arc::make_mut(node) {
+            Node::Leaf { data, weight, .. } => {
+                if let Some(pos) = data.iter().position(|arc| arc.0 == key) {
+                    data[pos] = Arc::new((key, value));
+                    false
+                } else {
+                    data.push(Arc::new((key, value)));
+                    *weight += 1;
+                    true
                 }
-            },
+            }
             Node::Branch { data, weight } => {
-                let (new_address, index) = address.shift().unwrap();
-                match data.get(index as usize) {
-                    None => {
-                        let new_node = Arc::new(Node::allocate(key, value, new_address));
-                        let mut new_data = data.clone();
-                        new_data.insert(index as usize, new_node);
-                        Arc::new(Node::Branch {
-                            data: new_data,
-                            weight: weight + 1,
-                        })
-                    }
-                    Some(next) => {
-                        let new_node = Node::insert(next, key, value, new_address);
-                        let mut new_data = data.clone();
-                        let add_weight = new_node.weight();
-                        new_data.insert(index as usize, new_node);
-                        Arc::new(Node::Branch {
-                            data: new_data,
-                            weight: weight + add_weight - next.weight(),
-                        })
-                    }
+                let (new_address, index) = address
+                    .shift()
+                    .expect("branches never sit below the last hash chunk");
+                let added = if let Some(next) = data.get_mut(index as usize) {
+                    Node::insert_mut(next, key, value, new_address)
+                } else {
+                    let new_node = Arc::new(Node::allocate(key, value, address.hash()));
+                    data.insert(index as usize, new_node);
+                    true
+                };
+                if added {
+                    *weight += 1;
                 }
+                added
             }
-        };
-        res
+        }
     }
 
-    pub fn merge(left: &Arc<Node<K, V>>, right: &Arc<Node<K, V>>) -> Arc<Node<K, V>> {
+    pub fn merge<R: Resolve<K, V>>(
+        left: &Arc<Node<K, V, B>>,
+        right: &Arc<Node<K, V, B>>,
+        resolve: &R,
+        shift: usize,
+    ) -> Arc<Node<K, V, B>> {
+        if R::IDEMPOTENT && Arc::ptr_eq(left, right) {
+            resolve.shared(left);
+            return Arc::clone(left);
+        }
+        if right.weight() == 0 {
+            return Arc::clone(left);
+        }
+        if left.weight() == 0 {
+            return Arc::clone(right);
+        }
+        if let Some([left, right]) = Node::align(left, right, shift) {
+            return Node::merge(&left, &right, resolve, shift);
+        }
         match (&**left, &**right) {
             (
                 Node::Leaf {
-                    data: left_data, ..
+                    hash,
+                    data: left_data,
+                    ..
                 },
                 Node::Leaf {
                     data: right_data, ..
@@ -128,13 +239,12 @@ impl<K: Eq, V> Node<K, V> {
                 let mut res = left_data.clone();
                 for r in right_data {
                     if let Some(p) = res.iter().position(|e| e.0 == r.0) {
-                        res[p] = Arc::clone(r);
+                        res[p] = resolve.resolve(&res[p], r);
                     } else {
                         res.push(Arc::clone(r));
                     }
                 }
-                let weight = res.len();
-                Arc::new(Node::Leaf { data: res, weight })
+                Arc::new(Node::leaf(*hash, res))
             }
             (
                 Node::Branch {
@@ -146,50 +256,501 @@ impl<K: Eq, V> Node<K, V> {
             ) => {
                 let mut res = left_data.clone();
                 for k in right_data.keys() {
-                    if let Some(node) = left_data.get(k) {
-                        res.insert(k, Node::merge(node, right_data.get(k).unwrap()));
+                    let r = right_data.get(k).unwrap();
+                    if let Some(l) = left_data.get(k) {
+                        res.insert(k, Node::merge(l, r, resolve, shift + Self::BITS));
                     } else {
-                        res.insert(k, right_data.get(k).unwrap().clone());
+                        res.insert(k, Arc::clone(r));
                     }
                 }
-                let weight = res
-                    .keys()
-                    .into_iter()
-                    .map(|k| res.get(k).unwrap().weight())
-                    .sum();
-                Arc::new(Node::Branch { data: res, weight })
+                Arc::new(Node::branch(res))
             }
-            _ => unreachable!(),
+            _ => unreachable!("aligned nodes have the same kind"),
         }
     }
 
-    pub fn get<Q>(&self, key: &Q, address: BitShifter) -> Option<&V>
-    where
-        K: Borrow<Q>,
-        Q: Eq + Hash,
-    {
+    pub fn intersection<R: Resolve<K, V>>(
+        left: &Arc<Node<K, V, B>>,
+        right: &Arc<Node<K, V, B>>,
+        resolve: &R,
+        shift: usize,
+    ) -> Arc<Node<K, V, B>> {
+        if R::IDEMPOTENT && Arc::ptr_eq(left, right) {
+            return Arc::clone(left);
+        }
+        if let Some([left, right]) = Node::align(left, right, shift) {
+            return Node::intersection(&left, &right, resolve, shift);
+        }
+        match (&**left, &**right) {
+            (
+                Node::Leaf {
+                    hash,
+                    data: left_data,
+                    ..
+                },
+                Node::Leaf {
+                    data: right_data, ..
+                },
+            ) => {
+                let res = left_data
+                    .iter()
+                    .filter_map(|l| {
+                        right_data
+                            .iter()
+                            .find(|r| r.0 == l.0)
+                            .map(|r| resolve.resolve(l, r))
+                    })
+                    .collect();
+                Arc::new(Node::leaf(*hash, res))
+            }
+            (
+                Node::Branch {
+                    data: left_data, ..
+                },
+                Node::Branch {
+                    data: right_data, ..
+                },
+            ) => {
+                let mut res = SparseVec::new();
+                for k in left_data.keys() {
+                    if let (Some(l), Some(r)) = (left_data.get(k), right_data.get(k)) {
+                        let node = Node::intersection(l, r, resolve, shift + Self::BITS);
+                        if node.weight() > 0 {
+                            res.insert(k, node);
+                        }
+                    }
+                }
+                Node::compact(res, shift)
+            }
+            _ => unreachable!("aligned nodes have the same kind"),
+        }
+    }
+
+    pub fn difference(
+        left: &Arc<Node<K, V, B>>,
+        right: &Arc<Node<K, V, B>>,
+        shift: usize,
+    ) -> Arc<Node<K, V, B>> {
+        if Arc::ptr_eq(left, right) {
+            return Arc::new(Node::default());
+        }
+        if right.weight() == 0 {
+            return Arc::clone(left);
+        }
+        if let Some([left, right]) = Node::align(left, right, shift) {
+            return Node::difference(&left, &right, shift);
+        }
+        match (&**left, &**right) {
+            (
+                Node::Leaf {
+                    hash,
+                    data: left_data,
+                    ..
+                },
+                Node::Leaf {
+                    data: right_data, ..
+                },
+            ) => {
+                let res = left_data
+                    .iter()
+                    .filter(|l| right_data.iter().all(|r| r.0 != l.0))
+                    .cloned()
+                    .collect();
+                Arc::new(Node::leaf(*hash, res))
+            }
+            (
+                Node::Branch {
+                    data: left_data, ..
+                },
+                Node::Branch {
+                    data: right_data, ..
+                },
+            ) => {
+                let mut res = left_data.clone();
+                for k in left_data.keys() {
+                    if let (Some(l), Some(r)) = (left_data.get(k), right_data.get(k)) {
+                        let node = Node::difference(l, r, shift + Self::BITS);
+                        if node.weight() == 0 {
+                            res.remove(k);
+                        } else {
+                            res.insert(k, node);
+                        }
+                    }
+                }
+                Node::compact(res, shift)
+            }
+            _ => unreachable!("aligned nodes have the same kind"),
+        }
+    }
+
+    pub fn symmetric_difference(
+        left: &Arc<Node<K, V, B>>,
+        right: &Arc<Node<K, V, B>>,
+        shift: usize,
+    ) -> Arc<Node<K, V, B>> {
+        if Arc::ptr_eq(left, right) {
+            return Arc::new(Node::default());
+        }
+        if right.weight() == 0 {
+            return Arc::clone(left);
+        }
+        if left.weight() == 0 {
+            return Arc::clone(right);
+        }
+        if let Some([left, right]) = Node::align(left, right, shift) {
+            return Node::symmetric_difference(&left, &right, shift);
+        }
+        match (&**left, &**right) {
+            (
+                Node::Leaf {
+                    hash,
+                    data: left_data,
+                    ..
+                },
+                Node::Leaf {
+                    data: right_data, ..
+                },
+            ) => {
+                let left_only = left_data
+                    .iter()
+                    .filter(|l| right_data.iter().all(|r| r.0 != l.0));
+                let right_only = right_data
+                    .iter()
+                    .filter(|r| left_data.iter().all(|l| l.0 != r.0));
+                let res = left_only.chain(right_only).cloned().collect();
+                Arc::new(Node::leaf(*hash, res))
+            }
+            (
+                Node::Branch {
+                    data: left_data, ..
+                },
+                Node::Branch {
+                    data: right_data, ..
+                },
+            ) => {
+                let mut res = left_data.clone();
+                for k in right_data.keys() {
+                    let r = right_data.get(k).unwrap();
+                    match left_data.get(k) {
+                        None => res.insert(k, Arc::clone(r)),
+                        Some(l) => {
+                            let node = Node::symmetric_difference(l, r, shift + Self::BITS);
+                            if node.weight() == 0 {
+                                res.remove(k);
+                            } else {
+                                res.insert(k, node);
+                            }
+                        }
+                    }
+                }
+                Node::compact(res, shift)
+            }
+            _ => unreachable!("aligned nodes have the same kind"),
+        }
+    }
+
+    pub fn find(
+        root: &Arc<Node<K, V, B>>,
+        address: BitShifter<B>,
+        mut is_match: impl FnMut(&K) -> bool,
+    ) -> (Path<'_, K, V, B>, Target<'_, K, V, B>) {
+        let mut path = Path {
+            steps: SmallVec::new(),
+        };
+        let mut node = root;
+        let mut address = address;
+        loop {
+            match &**node {
+                Node::Leaf { hash, data, .. } => {
+                    let position = if *hash == address.hash() {
+                        data.iter().position(|arc| is_match(&arc.0))
+                    } else {
+                        None
+                    };
+                    let target = match position {
+                        Some(position) => Target::Found(Found {
+                            hash: *hash,
+                            data,
+                            position,
+                        }),
+                        None => Target::Missing(Missing {
+                            leaf: Some(node),
+                            address,
+                        }),
+                    };
+                    return (path, target);
+                }
+                Node::Branch { data, .. } => {
+                    let (new_address, index) = address
+                        .shift()
+                        .expect("branches never sit below the last hash chunk");
+                    path.steps.push((data, index as usize));
+                    let Some(next) = data.get(index as usize) else {
+                        let target = Target::Missing(Missing {
+                            leaf: None,
+                            address: new_address,
+                        });
+                        return (path, target);
+                    };
+                    node = next;
+                    address = new_address;
+                }
+            }
+        }
+    }
+
+    pub fn get(
+        &self,
+        address: BitShifter<B>,
+        mut is_match: impl FnMut(&K) -> bool,
+    ) -> Option<&Arc<(K, V)>> {
         match self {
-            Node::Leaf { data, .. } => data
-                .iter()
-                .find(|arc| arc.0.borrow() == key)
-                .map(|arc| &arc.1),
+            Node::Leaf { hash, data, .. } => {
+                if *hash == address.hash() {
+                    data.iter().find(|arc| is_match(&arc.0))
+                } else {
+                    None
+                }
+            }
             Node::Branch { data, .. } => {
-                let (new_address, index) = address.shift().unwrap();
+                let (new_address, index) = address.shift()?;
                 data.get(index as usize)
-                    .and_then(|node| node.get(key, new_address))
+                    .and_then(|node| node.get(new_address, is_match))
             }
         }
     }
 }
 
-impl<K: Debug, V: Debug> Debug for Node<K, V> {
+impl<K, V, const B: usize> Node<K, V, B> {
+    /// Keeps the entries accepted by `keep`. A subtree that loses nothing is
+    /// returned as it is, so only the paths leading to removals are copied.
+    pub fn filter<F: FnMut(&K, &V) -> bool>(
+        node: &Arc<Node<K, V, B>>,
+        keep: &mut F,
+        shift: usize,
+    ) -> Arc<Node<K, V, B>> {
+        match &**node {
+            Node::Leaf { hash, data, .. } => {
+                let res: Entries<K, V> = data
+                    .iter()
+                    .filter(|entry| keep(&entry.0, &entry.1))
+                    .cloned()
+                    .collect();
+                if res.len() == data.len() {
+                    Arc::clone(node)
+                } else {
+                    Arc::new(Node::leaf(*hash, res))
+                }
+            }
+            Node::Branch { data, .. } => {
+                let mut res: Option<Children<K, V, B>> = None;
+                for k in data.keys() {
+                    let child = data.get(k).unwrap();
+                    let filtered = Node::filter(child, keep, shift + Self::BITS);
+                    if Arc::ptr_eq(&filtered, child) {
+                        continue;
+                    }
+                    let res = res.get_or_insert_with(|| data.clone());
+                    if filtered.weight() == 0 {
+                        res.remove(k);
+                    } else {
+                        res.insert(k, filtered);
+                    }
+                }
+                match res {
+                    Some(res) => Node::compact(res, shift),
+                    None => Arc::clone(node),
+                }
+            }
+        }
+    }
+
+    /// Splits the entries into those accepted by `pred` and the rest. Every
+    /// subtree that ends up entirely on one side is reused on that side.
+    pub fn partition<F: FnMut(&K, &V) -> bool>(
+        node: &Arc<Node<K, V, B>>,
+        pred: &mut F,
+        shift: usize,
+    ) -> [Arc<Node<K, V, B>>; 2] {
+        let [accepted, rejected] = match &**node {
+            Node::Leaf { hash, data, .. } => {
+                let (accepted, rejected): (Entries<K, V>, Entries<K, V>) = data
+                    .iter()
+                    .cloned()
+                    .partition(|entry| pred(&entry.0, &entry.1));
+                [accepted, rejected].map(|side| Node::leaf(*hash, side))
+            }
+            Node::Branch { data, .. } => {
+                let mut sides = [SparseVec::new(), SparseVec::new()];
+                for k in data.keys() {
+                    let child = data.get(k).unwrap();
+                    let parts = Node::partition(child, pred, shift + Self::BITS);
+                    for (side, part) in sides.iter_mut().zip(parts) {
+                        if part.weight() > 0 {
+                            side.insert(k, part);
+                        }
+                    }
+                }
+                sides.map(Node::branch)
+            }
+        };
+        if rejected.weight() == 0 {
+            return [Arc::clone(node), Arc::new(Node::default())];
+        }
+        if accepted.weight() == 0 {
+            return [Arc::new(Node::default()), Arc::clone(node)];
+        }
+        [accepted, rejected].map(|side| match side {
+            Node::Branch { data, .. } => Node::compact(data, shift),
+            leaf @ Node::Leaf { .. } => Arc::new(leaf),
+        })
+    }
+
+    /// Rebuilds the trie with `f` applied to every entry. The hashes do not
+    /// change, so the entries stay where they are and nothing is rehashed.
+    pub fn filter_map<W, F: FnMut(&K, &V) -> Option<W>>(
+        node: &Node<K, V, B>,
+        f: &mut F,
+        shift: usize,
+    ) -> Arc<Node<K, W, B>>
+    where
+        K: Clone,
+    {
+        match node {
+            Node::Leaf { hash, data, .. } => {
+                let res = data
+                    .iter()
+                    .filter_map(|entry| {
+                        f(&entry.0, &entry.1).map(|w| Arc::new((entry.0.clone(), w)))
+                    })
+                    .collect();
+                Arc::new(Node::leaf(*hash, res))
+            }
+            Node::Branch { data, .. } => {
+                let mut res = SparseVec::new();
+                for k in data.keys() {
+                    let mapped = Node::filter_map(data.get(k).unwrap(), f, shift + Self::BITS);
+                    if mapped.weight() > 0 {
+                        res.insert(k, mapped);
+                    }
+                }
+                Node::compact(res, shift)
+            }
+        }
+    }
+}
+
+pub trait Resolve<K, V> {
+    /// Whether resolving an entry against itself always yields that entry,
+    /// which allows pointer-equal subtrees to be reused without visiting them.
+    const IDEMPOTENT: bool;
+
+    fn resolve(&self, left: &Arc<(K, V)>, right: &Arc<(K, V)>) -> Arc<(K, V)>;
+
+    /// Called with every subtree a merge reuses because both sides share it,
+    /// so its entries are never passed to `resolve`.
+    fn shared<const B: usize>(&self, _node: &Node<K, V, B>) {}
+}
+
+pub struct TakeRight;
+
+impl<K, V> Resolve<K, V> for TakeRight {
+    const IDEMPOTENT: bool = true;
+
+    fn resolve(&self, _: &Arc<(K, V)>, right: &Arc<(K, V)>) -> Arc<(K, V)> {
+        Arc::clone(right)
+    }
+}
+
+pub struct ResolveWith<F>(pub F);
+
+impl<K, V, F> Resolve<K, V> for ResolveWith<F>
+where
+    K: Clone,
+    F: Fn(&K, &V, &V) -> V,
+{
+    const IDEMPOTENT: bool = false;
+
+    fn resolve(&self, left: &Arc<(K, V)>, right: &Arc<(K, V)>) -> Arc<(K, V)> {
+        let value = (self.0)(&left.0, &left.1, &right.1);
+        Arc::new((left.0.clone(), value))
+    }
+}
+
+pub struct Path<'a, K, V, const B: usize> {
+    steps: SmallVec<[(&'a Children<K, V, B>, usize); 16]>,
+}
+
+impl<K, V, const B: usize> Path<'_, K, V, B> {
+    pub fn rebuild(&self, node: Arc<Node<K, V, B>>) -> Arc<Node<K, V, B>> {
+        self.steps
+            .iter()
+            .enumerate()
+            .rev()
+            .fold(node, |node, (depth, (data, index))| {
+                let mut new_data = (*data).clone();
+                if node.weight() == 0 {
+                    new_data.remove(*index);
+                } else {
+                    new_data.insert(*index, node);
+                }
+                Node::compact(new_data, depth * Node::<K, V, B>::BITS)
+            })
+    }
+}
+
+pub enum Target<'a, K, V, const B: usize> {
+    Found(Found<'a, K, V, B>),
+    Missing(Missing<'a, K, V, B>),
+}
+
+pub struct Found<'a, K, V, const B: usize> {
+    hash: u64,
+    data: &'a Entries<K, V>,
+    position: usize,
+}
+
+impl<'a, K, V, const B: usize> Found<'a, K, V, B> {
+    pub fn get(&self) -> &'a Arc<(K, V)> {
+        &self.data[self.position]
+    }
+
+    pub fn replace(&self, key: K, value: V) -> Arc<Node<K, V, B>> {
+        let mut new_data = self.data.clone();
+        new_data[self.position] = Arc::new((key, value));
+        Arc::new(Node::leaf(self.hash, new_data))
+    }
+
+    pub fn remove(&self) -> Arc<Node<K, V, B>> {
+        let mut new_data = self.data.clone();
+        new_data.remove(self.position);
+        Arc::new(Node::leaf(self.hash, new_data))
+    }
+}
+
+pub struct Missing<'a, K, V, const B: usize> {
+    leaf: Option<&'a Arc<Node<K, V, B>>>,
+    address: BitShifter<B>,
+}
+
+impl<K: Eq, V, const B: usize> Missing<'_, K, V, B> {
+    pub fn insert(&self, key: K, value: V) -> Arc<Node<K, V, B>> {
+        let new_node = Arc::new(Node::allocate(key, value, self.address.hash()));
+        match self.leaf {
+            Some(leaf) => Node::merge(leaf, &new_node, &TakeRight, self.address.offset()),
+            None => new_node,
+        }
+    }
+}
+
+impl<K: Debug, V: Debug, const B: usize> Debug for Node<K, V, B> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         inner_print(self, f, "")
     }
 }
 
-fn inner_print<K: Debug, V: Debug>(
-    node: &Node<K, V>,
+fn inner_print<K: Debug, V: Debug, const B: usize>(
+    node: &Node<K, V, B>,
     f: &mut Formatter<'_>,
     prefix: &str,
 ) -> fmt::Result {
@@ -206,23 +767,45 @@ fn inner_print<K: Debug, V: Debug>(
 }
 
 #[derive(Clone, Copy)]
-pub struct BitShifter {
-    value: u64,
+pub struct BitShifter<const B: usize> {
+    hash: u64,
     shift: usize,
 }
 
-impl BitShifter {
-    pub fn new(value: u64) -> Self {
-        Self { value, shift: 0 }
+impl<const B: usize> BitShifter<B> {
+    /// Every level of a trie with `B` children per branch consumes
+    /// `log2(B)` bits of the hash.
+    pub const BITS: usize = {
+        assert!(
+            B.is_power_of_two() && B >= 2 && B <= 64,
+            "the branching factor has to be a power of two between 2 and 64"
+        );
+        B.trailing_zeros() as usize
+    };
+
+    pub fn new(hash: u64) -> Self {
+        Self { hash, shift: 0 }
+    }
+
+    pub fn at(hash: u64, shift: usize) -> Self {
+        Self { hash, shift }
+    }
+
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    pub fn offset(&self) -> usize {
+        self.shift
     }
 
-    fn shift(&self) -> Option<(BitShifter, u64)> {
+    fn shift(&self) -> Option<(BitShifter<B>, u64)> {
         if self.shift < 64 {
-            let res = self.value & 0b1111;
+            let res = (self.hash >> self.shift) & (B as u64 - 1);
             Some((
                 BitShifter {
-                    value: self.value >> 4,
-                    shift: self.shift + 4,
+                    hash: self.hash,
+                    shift: self.shift + Self::BITS,
                 },
                 res,
             ))
@@ -232,10 +815,10 @@ impl BitShifter {
     }
 }
 
-impl Debug for BitShifter {
+impl<const B: usize> Debug for BitShifter<B> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         if self.shift < 64 {
-            write!(f, "{:16x}", self.value)
+            write!(f, "{:16x}", self.hash >> self.shift)
         } else {
             write!(f, "X")
         }
@@ -0,0 +1,236 @@
+use std::{
+    borrow::Borrow,
+    hash::{Hash, Hasher},
+    ops::{Bound, RangeBounds},
+    sync::Arc,
+};
+
+use crate::ord_nodes::{precedes, Node, Tree};
+
+/// A persistent map ordered by key, kept as an AVL tree with one entry per
+/// node. Updates copy the path from the root to the changed entry and share
+/// everything else.
+#[derive(Debug)]
+pub struct PerOrdMap<K, V> {
+    pub(crate) data: Tree<K, V>,
+}
+
+impl<K, V> PerOrdMap<K, V> {
+    #[must_use]
+    pub fn empty() -> Self {
+        PerOrdMap { data: None }
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        Node::size(&self.data)
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_none()
+    }
+
+    #[must_use]
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        let mut iter = Iter {
+            stack: Vec::new(),
+            remaining: self.len(),
+        };
+        iter.push_left(&self.data);
+        iter
+    }
+
+    #[must_use]
+    pub fn first(&self) -> Option<&Arc<(K, V)>> {
+        Node::first(&self.data)
+    }
+
+    #[must_use]
+    pub fn last(&self) -> Option<&Arc<(K, V)>> {
+        Node::last(&self.data)
+    }
+}
+
+impl<K, V> Clone for PerOrdMap<K, V> {
+    fn clone(&self) -> Self {
+        PerOrdMap {
+            data: self.data.clone(),
+        }
+    }
+}
+
+impl<K, V> Default for PerOrdMap<K, V> {
+    fn default() -> Self {
+        PerOrdMap::empty()
+    }
+}
+
+impl<K: Ord, V> PerOrdMap<K, V> {
+    #[must_use]
+    pub fn insert(&self, key: K, value: V) -> Self {
+        PerOrdMap {
+            data: Some(Node::insert(&self.data, Arc::new((key, value)))),
+        }
+    }
+
+    #[must_use]
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        Node::get(&self.data, key).map(|entry| &entry.1)
+    }
+
+    #[must_use]
+    pub fn remove<Q>(&self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match Node::remove(&self.data, key) {
+            Some(data) => PerOrdMap { data },
+            None => self.clone(),
+        }
+    }
+
+    pub fn range<Q, R>(&self, range: R) -> Iter<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let start = Node::rank(&self.data, range.start_bound());
+        let end = match range.end_bound() {
+            Bound::Included(key) => Node::rank(&self.data, Bound::Excluded(key)),
+            Bound::Excluded(key) => Node::rank(&self.data, Bound::Included(key)),
+            Bound::Unbounded => self.len(),
+        };
+        let mut iter = Iter {
+            stack: Vec::new(),
+            remaining: end.saturating_sub(start),
+        };
+        let (key, inclusive) = match range.start_bound() {
+            Bound::Included(key) => (key, false),
+            Bound::Excluded(key) => (key, true),
+            Bound::Unbounded => {
+                iter.push_left(&self.data);
+                return iter;
+            }
+        };
+        let mut current = &self.data;
+        while let Some(node) = current {
+            if precedes(node.entry.0.borrow(), key, inclusive) {
+                current = &node.right;
+            } else {
+                iter.stack.push(node);
+                current = &node.left;
+            }
+        }
+        iter
+    }
+
+    /// Splits the map in two: the entries with keys before `key` and those
+    /// with keys from `key` on. Unlike `BTreeMap::split_off` it leaves `self`
+    /// untouched and returns both halves.
+    #[must_use]
+    pub fn split_off<Q>(&self, key: &Q) -> (Self, Self)
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (left, found, right) = Node::split(&self.data, key);
+        let right = match found {
+            Some(entry) => Some(Node::join(None, entry, right)),
+            None => right,
+        };
+        (PerOrdMap { data: left }, PerOrdMap { data: right })
+    }
+}
+
+impl<K: Eq, V: PartialEq> PartialEq for PerOrdMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.data, &other.data) {
+            (Some(left), Some(right)) if Arc::ptr_eq(left, right) => true,
+            _ => {
+                self.len() == other.len()
+                    && self
+                        .iter()
+                        .zip(other)
+                        .all(|(l, r)| Arc::ptr_eq(l, r) || (l.0 == r.0 && l.1 == r.1))
+            }
+        }
+    }
+}
+
+impl<K: Eq, V: Eq> Eq for PerOrdMap<K, V> {}
+
+impl<K: Hash, V: Hash> Hash for PerOrdMap<K, V> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len());
+        for entry in self {
+            entry.hash(state);
+        }
+    }
+}
+
+impl<K: Ord, V> FromIterator<(K, V)> for PerOrdMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = PerOrdMap::empty();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Ord, V> Extend<(K, V)> for PerOrdMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.data = Some(Node::insert(&self.data, Arc::new((key, value))));
+        }
+    }
+}
+
+pub struct Iter<'a, K, V> {
+    stack: Vec<&'a Node<K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iter<'a, K, V> {
+    fn push_left(&mut self, mut tree: &'a Tree<K, V>) {
+        while let Some(node) = tree {
+            self.stack.push(node);
+            tree = &node.left;
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = &'a Arc<(K, V)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let node = self.stack.pop()?;
+        self.push_left(&node.right);
+        self.remaining -= 1;
+        Some(&node.entry)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<'a, K, V> IntoIterator for &'a PerOrdMap<K, V> {
+    type Item = &'a Arc<(K, V)>;
+
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
@@ -0,0 +1,265 @@
+use std::{borrow::Borrow, cmp::Ordering, ops::Bound, sync::Arc};
+
+pub type Tree<K, V> = Option<Arc<Node<K, V>>>;
+
+type Split<K, V> = (Tree<K, V>, Option<Arc<(K, V)>>, Tree<K, V>);
+
+#[derive(Debug)]
+pub struct Node<K, V> {
+    pub(crate) entry: Arc<(K, V)>,
+    pub(crate) left: Tree<K, V>,
+    pub(crate) right: Tree<K, V>,
+    height: u8,
+    size: usize,
+}
+
+impl<K, V> Node<K, V> {
+    pub fn height(tree: &Tree<K, V>) -> u8 {
+        tree.as_ref().map_or(0, |node| node.height)
+    }
+
+    pub fn size(tree: &Tree<K, V>) -> usize {
+        tree.as_ref().map_or(0, |node| node.size)
+    }
+
+    fn new(left: Tree<K, V>, entry: Arc<(K, V)>, right: Tree<K, V>) -> Arc<Node<K, V>> {
+        Arc::new(Node {
+            height: Node::height(&left).max(Node::height(&right)) + 1,
+            size: Node::size(&left) + Node::size(&right) + 1,
+            entry,
+            left,
+            right,
+        })
+    }
+
+    /// Builds a node whose subtrees differ in height by at most two, restoring
+    /// the AVL invariant with a single or double rotation.
+    fn balance(left: Tree<K, V>, entry: Arc<(K, V)>, right: Tree<K, V>) -> Arc<Node<K, V>> {
+        let (left_height, right_height) = (Node::height(&left), Node::height(&right));
+        if left_height > right_height + 1 {
+            let l = left.expect("taller subtree is not empty");
+            if Node::height(&l.left) >= Node::height(&l.right) {
+                Node::new(
+                    l.left.clone(),
+                    Arc::clone(&l.entry),
+                    Some(Node::new(l.right.clone(), entry, right)),
+                )
+            } else {
+                let lr = l.right.as_ref().expect("taller subtree is not empty");
+                Node::new(
+                    Some(Node::new(
+                        l.left.clone(),
+                        Arc::clone(&l.entry),
+                        lr.left.clone(),
+                    )),
+                    Arc::clone(&lr.entry),
+                    Some(Node::new(lr.right.clone(), entry, right)),
+                )
+            }
+        } else if right_height > left_height + 1 {
+            let r = right.expect("taller subtree is not empty");
+            if Node::height(&r.right) >= Node::height(&r.left) {
+                Node::new(
+                    Some(Node::new(left, entry, r.left.clone())),
+                    Arc::clone(&r.entry),
+                    r.right.clone(),
+                )
+            } else {
+                let rl = r.left.as_ref().expect("taller subtree is not empty");
+                Node::new(
+                    Some(Node::new(left, entry, rl.left.clone())),
+                    Arc::clone(&rl.entry),
+                    Some(Node::new(
+                        rl.right.clone(),
+                        Arc::clone(&r.entry),
+                        r.right.clone(),
+                    )),
+                )
+            }
+        } else {
+            Node::new(left, entry, right)
+        }
+    }
+
+    /// Joins two trees of arbitrary heights around an entry that sorts between
+    /// them by descending the spine of the taller one.
+    pub fn join(left: Tree<K, V>, entry: Arc<(K, V)>, right: Tree<K, V>) -> Arc<Node<K, V>> {
+        let (left_height, right_height) = (Node::height(&left), Node::height(&right));
+        if left_height > right_height + 1 {
+            let l = left.expect("taller subtree is not empty");
+            Node::balance(
+                l.left.clone(),
+                Arc::clone(&l.entry),
+                Some(Node::join(l.right.clone(), entry, right)),
+            )
+        } else if right_height > left_height + 1 {
+            let r = right.expect("taller subtree is not empty");
+            Node::balance(
+                Some(Node::join(left, entry, r.left.clone())),
+                Arc::clone(&r.entry),
+                r.right.clone(),
+            )
+        } else {
+            Node::new(left, entry, right)
+        }
+    }
+
+    fn pop_last(node: &Arc<Node<K, V>>) -> (Tree<K, V>, Arc<(K, V)>) {
+        match &node.right {
+            None => (node.left.clone(), Arc::clone(&node.entry)),
+            Some(right) => {
+                let (right, last) = Node::pop_last(right);
+                let rest = Node::balance(node.left.clone(), Arc::clone(&node.entry), right);
+                (Some(rest), last)
+            }
+        }
+    }
+
+    fn concat(left: Tree<K, V>, right: Tree<K, V>) -> Tree<K, V> {
+        match left {
+            None => right,
+            Some(left) => {
+                let (left, last) = Node::pop_last(&left);
+                Some(Node::join(left, last, right))
+            }
+        }
+    }
+
+    pub fn first(tree: &Tree<K, V>) -> Option<&Arc<(K, V)>> {
+        let mut node = tree.as_ref()?;
+        while let Some(left) = &node.left {
+            node = left;
+        }
+        Some(&node.entry)
+    }
+
+    pub fn last(tree: &Tree<K, V>) -> Option<&Arc<(K, V)>> {
+        let mut node = tree.as_ref()?;
+        while let Some(right) = &node.right {
+            node = right;
+        }
+        Some(&node.entry)
+    }
+
+    pub fn get<'a, Q>(tree: &'a Tree<K, V>, key: &Q) -> Option<&'a Arc<(K, V)>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut current = tree;
+        while let Some(node) = current {
+            match key.cmp(node.entry.0.borrow()) {
+                Ordering::Less => current = &node.left,
+                Ordering::Greater => current = &node.right,
+                Ordering::Equal => return Some(&node.entry),
+            }
+        }
+        None
+    }
+
+    pub fn insert(tree: &Tree<K, V>, entry: Arc<(K, V)>) -> Arc<Node<K, V>>
+    where
+        K: Ord,
+    {
+        let Some(node) = tree else {
+            return Node::new(None, entry, None);
+        };
+        match entry.0.cmp(&node.entry.0) {
+            Ordering::Less => Node::balance(
+                Some(Node::insert(&node.left, entry)),
+                Arc::clone(&node.entry),
+                node.right.clone(),
+            ),
+            Ordering::Greater => Node::balance(
+                node.left.clone(),
+                Arc::clone(&node.entry),
+                Some(Node::insert(&node.right, entry)),
+            ),
+            Ordering::Equal => Node::new(node.left.clone(), entry, node.right.clone()),
+        }
+    }
+
+    /// Returns `None` when the key is absent, so that callers can keep the
+    /// original tree.
+    pub fn remove<Q>(tree: &Tree<K, V>, key: &Q) -> Option<Tree<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let node = tree.as_ref()?;
+        let res = match key.cmp(node.entry.0.borrow()) {
+            Ordering::Less => Node::balance(
+                Node::remove(&node.left, key)?,
+                Arc::clone(&node.entry),
+                node.right.clone(),
+            ),
+            Ordering::Greater => Node::balance(
+                node.left.clone(),
+                Arc::clone(&node.entry),
+                Node::remove(&node.right, key)?,
+            ),
+            Ordering::Equal => return Some(Node::concat(node.left.clone(), node.right.clone())),
+        };
+        Some(Some(res))
+    }
+
+    pub fn split<Q>(tree: &Tree<K, V>, key: &Q) -> Split<K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let Some(node) = tree else {
+            return (None, None, None);
+        };
+        match key.cmp(node.entry.0.borrow()) {
+            Ordering::Less => {
+                let (left, found, right) = Node::split(&node.left, key);
+                let right = Node::join(right, Arc::clone(&node.entry), node.right.clone());
+                (left, found, Some(right))
+            }
+            Ordering::Greater => {
+                let (left, found, right) = Node::split(&node.right, key);
+                let left = Node::join(node.left.clone(), Arc::clone(&node.entry), left);
+                (Some(left), found, right)
+            }
+            Ordering::Equal => (
+                node.left.clone(),
+                Some(Arc::clone(&node.entry)),
+                node.right.clone(),
+            ),
+        }
+    }
+
+    /// Counts the entries that come before `bound`, with an included start
+    /// bound excluding its key and an excluded start bound including it.
+    pub fn rank<Q>(tree: &Tree<K, V>, bound: Bound<&Q>) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (key, inclusive) = match bound {
+            Bound::Included(key) => (key, false),
+            Bound::Excluded(key) => (key, true),
+            Bound::Unbounded => return 0,
+        };
+        let mut current = tree;
+        let mut count = 0;
+        while let Some(node) = current {
+            if precedes(node.entry.0.borrow(), key, inclusive) {
+                count += Node::size(&node.left) + 1;
+                current = &node.right;
+            } else {
+                current = &node.left;
+            }
+        }
+        count
+    }
+}
+
+pub fn precedes<Q: Ord + ?Sized>(candidate: &Q, key: &Q, inclusive: bool) -> bool {
+    match candidate.cmp(key) {
+        Ordering::Less => true,
+        Ordering::Equal => inclusive,
+        Ordering::Greater => false,
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    borrow::Borrow,
+    hash::{Hash, Hasher},
+    ops::RangeBounds,
+};
+
+use crate::{ord_map, Element, PerOrdMap};
+
+/// A persistent ordered set, backed by a [`PerOrdMap`] with unit values.
+#[derive(Debug, Clone)]
+pub struct PerOrdSet<K>(pub(crate) PerOrdMap<K, ()>);
+
+impl<K> PerOrdSet<K> {
+    #[must_use]
+    pub fn empty() -> Self {
+        PerOrdSet(PerOrdMap::empty())
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    #[must_use]
+    pub fn iter(&self) -> Iter<'_, K> {
+        Iter(self.0.iter())
+    }
+
+    #[must_use]
+    pub fn first(&self) -> Option<&K> {
+        self.0.first().map(|entry| &entry.0)
+    }
+
+    #[must_use]
+    pub fn last(&self) -> Option<&K> {
+        self.0.last().map(|entry| &entry.0)
+    }
+}
+
+impl<K> Default for PerOrdSet<K> {
+    fn default() -> Self {
+        PerOrdSet::empty()
+    }
+}
+
+impl<K: Ord> PerOrdSet<K> {
+    #[must_use]
+    pub fn insert(&self, key: K) -> Self {
+        PerOrdSet(self.0.insert(key, ()))
+    }
+
+    #[must_use]
+    pub fn remove<Q>(&self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        PerOrdSet(self.0.remove(key))
+    }
+
+    #[must_use]
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.0.get(key).is_some()
+    }
+
+    pub fn range<Q, R>(&self, range: R) -> Iter<'_, K>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        Iter(self.0.range(range))
+    }
+
+    /// Returns the elements before `key` and those from `key` on, leaving
+    /// `self` untouched.
+    #[must_use]
+    pub fn split_off<Q>(&self, key: &Q) -> (Self, Self)
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let (left, right) = self.0.split_off(key);
+        (PerOrdSet(left), PerOrdSet(right))
+    }
+}
+
+impl<K: Eq> PartialEq for PerOrdSet<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<K: Eq> Eq for PerOrdSet<K> {}
+
+impl<K: Hash> Hash for PerOrdSet<K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl<K: Ord> FromIterator<K> for PerOrdSet<K> {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        PerOrdSet(iter.into_iter().map(|key| (key, ())).collect())
+    }
+}
+
+impl<K: Ord> Extend<K> for PerOrdSet<K> {
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        self.0.extend(iter.into_iter().map(|key| (key, ())));
+    }
+}
+
+pub struct Iter<'a, K>(ord_map::Iter<'a, K, ()>);
+
+impl<'a, K> Iterator for Iter<'a, K> {
+    type Item = Element<'a, K>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(Element)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K> ExactSizeIterator for Iter<'_, K> {}
+
+impl<'a, K> IntoIterator for &'a PerOrdSet<K> {
+    type Item = Element<'a, K>;
+
+    type IntoIter = Iter<'a, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
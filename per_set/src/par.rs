@@ -0,0 +1,149 @@
+use std::{
+    hash::{BuildHasher, Hash},
+    sync::Arc,
+};
+
+use rayon::iter::{
+    plumbing::UnindexedConsumer, FromParallelIterator, IntoParallelIterator, ParallelIterator,
+};
+use sparse_vec::SparseVec;
+
+use crate::{
+    iter::Iter,
+    nodes::{Node, Resolve, ResolveWith, TakeRight},
+    PerMap, PerMapBuilder,
+};
+
+/// Parallel iterator over the entries of a map. The work is split over the
+/// subtrees found in the two topmost levels of the trie.
+pub struct ParIter<'a, K, V, const B: usize = 16> {
+    nodes: Vec<&'a Node<K, V, B>>,
+}
+
+impl<'a, K, V, const B: usize> ParIter<'a, K, V, B> {
+    fn new(root: &'a Node<K, V, B>) -> Self {
+        let mut nodes = Vec::new();
+        if let Node::Branch { data, .. } = root {
+            for child in data {
+                match &**child {
+                    Node::Branch { data, .. } => nodes.extend(data.iter().map(|n| &**n)),
+                    Node::Leaf { .. } => nodes.push(&**child),
+                }
+            }
+        }
+        ParIter { nodes }
+    }
+}
+
+impl<'a, K: Send + Sync, V: Send + Sync, const B: usize> ParallelIterator for ParIter<'a, K, V, B> {
+    type Item = &'a Arc<(K, V)>;
+
+    fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(self, consumer: C) -> C::Result {
+        self.nodes
+            .into_par_iter()
+            .flat_map_iter(Iter::from_node)
+            .drive_unindexed(consumer)
+    }
+}
+
+impl<'a, K: Send + Sync, V: Send + Sync, S, const B: usize> IntoParallelIterator
+    for &'a PerMap<K, V, S, B>
+{
+    type Item = &'a Arc<(K, V)>;
+
+    type Iter = ParIter<'a, K, V, B>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        ParIter::new(&self.data)
+    }
+}
+
+impl<K, V, S, const B: usize> PerMap<K, V, S, B> {
+    pub fn par_iter(&self) -> ParIter<'_, K, V, B> {
+        ParIter::new(&self.data)
+    }
+}
+
+impl<K, V, S, const B: usize> PerMap<K, V, S, B>
+where
+    K: Eq + Hash + Send + Sync,
+    V: Send + Sync,
+    S: BuildHasher + Clone,
+{
+    #[must_use]
+    pub fn par_union(&self, other: &PerMap<K, V, S, B>) -> Self {
+        self.with_data(Node::par_merge(&self.data, &other.data, &TakeRight))
+    }
+
+    #[must_use]
+    pub fn par_union_with<F>(&self, other: &PerMap<K, V, S, B>, f: F) -> Self
+    where
+        K: Clone,
+        F: Fn(&K, &V, &V) -> V + Sync,
+    {
+        self.with_data(Node::par_merge(&self.data, &other.data, &ResolveWith(f)))
+    }
+}
+
+impl<K: Eq + Send + Sync, V: Send + Sync, const B: usize> Node<K, V, B> {
+    /// Merges two roots by handing each of the top-level children to its own
+    /// task, which then continues with the sequential merge.
+    fn par_merge<R: Resolve<K, V> + Sync>(
+        left: &Arc<Node<K, V, B>>,
+        right: &Arc<Node<K, V, B>>,
+        resolve: &R,
+    ) -> Arc<Node<K, V, B>> {
+        if R::IDEMPOTENT && Arc::ptr_eq(left, right) {
+            resolve.shared(left);
+            return Arc::clone(left);
+        }
+        if right.weight() == 0 {
+            return Arc::clone(left);
+        }
+        if left.weight() == 0 {
+            return Arc::clone(right);
+        }
+        let (Node::Branch { data: left, .. }, Node::Branch { data: right, .. }) =
+            (&**left, &**right)
+        else {
+            unreachable!("roots are always branches");
+        };
+        let children = (0..B)
+            .into_par_iter()
+            .filter_map(|k| match (left.get(k), right.get(k)) {
+                (Some(l), Some(r)) => Some((k, Node::merge(l, r, resolve, Self::BITS))),
+                (Some(node), None) | (None, Some(node)) => Some((k, Arc::clone(node))),
+                (None, None) => None,
+            })
+            .collect::<Vec<_>>();
+        let mut data = SparseVec::new();
+        for (k, child) in children {
+            data.insert(k, child);
+        }
+        Arc::new(Node::branch(data))
+    }
+}
+
+impl<K, V, S, const B: usize> FromParallelIterator<(K, V)> for PerMap<K, V, S, B>
+where
+    K: Eq + Hash + Send + Sync,
+    V: Send + Sync,
+    S: BuildHasher + Clone + Default + Send,
+{
+    fn from_par_iter<I: IntoParallelIterator<Item = (K, V)>>(par_iter: I) -> Self {
+        par_iter
+            .into_par_iter()
+            .fold(
+                || PerMapBuilder::with_hasher(S::default()),
+                |mut builder, (key, value)| {
+                    builder.insert(key, value);
+                    builder
+                },
+            )
+            .map(PerMapBuilder::build)
+            .reduce(
+                || PerMap::with_hasher(S::default()),
+                |left, right| left.par_union(&right),
+            )
+    }
+}
@@ -0,0 +1,186 @@
+use std::{
+    borrow::Borrow,
+    hash::{BuildHasher, Hash},
+    sync::Arc,
+};
+
+use crate::{
+    nodes::{BitShifter, Found, Missing, Node, Path, Target},
+    PerMap,
+};
+
+/// Looks up entries by a hash and an equality check supplied by the caller,
+/// like hashbrown's raw entry API. See [`PerMap::get_with_hash`] for what is
+/// expected of the hash.
+pub struct RawEntryBuilder<'a, K, V, S, const B: usize = 16> {
+    map: &'a PerMap<K, V, S, B>,
+}
+
+pub enum RawEntry<'a, K, V, S, const B: usize = 16> {
+    Occupied(RawOccupiedEntry<'a, K, V, S, B>),
+    Vacant(RawVacantEntry<'a, K, V, S, B>),
+}
+
+pub struct RawOccupiedEntry<'a, K, V, S, const B: usize = 16> {
+    map: &'a PerMap<K, V, S, B>,
+    path: Path<'a, K, V, B>,
+    target: Found<'a, K, V, B>,
+}
+
+pub struct RawVacantEntry<'a, K, V, S, const B: usize = 16> {
+    map: &'a PerMap<K, V, S, B>,
+    hash: u64,
+    path: Path<'a, K, V, B>,
+    target: Missing<'a, K, V, B>,
+}
+
+impl<K, V, S, const B: usize> PerMap<K, V, S, B> {
+    pub fn raw_entry(&self) -> RawEntryBuilder<'_, K, V, S, B> {
+        RawEntryBuilder { map: self }
+    }
+}
+
+impl<'a, K: Eq, V, S, const B: usize> RawEntryBuilder<'a, K, V, S, B> {
+    pub fn from_key<Q>(self, key: &Q) -> RawEntry<'a, K, V, S, B>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+        S: BuildHasher,
+    {
+        let hash = self.map.hasher.hash_one(key);
+        self.from_key_hashed_nocheck(hash, key)
+    }
+
+    pub fn from_key_hashed_nocheck<Q>(self, hash: u64, key: &Q) -> RawEntry<'a, K, V, S, B>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.from_hash(hash, |k| k.borrow() == key)
+    }
+
+    pub fn from_hash<F>(self, hash: u64, is_match: F) -> RawEntry<'a, K, V, S, B>
+    where
+        F: FnMut(&K) -> bool,
+    {
+        match Node::find(&self.map.data, BitShifter::new(hash), is_match) {
+            (path, Target::Found(target)) => RawEntry::Occupied(RawOccupiedEntry {
+                map: self.map,
+                path,
+                target,
+            }),
+            (path, Target::Missing(target)) => RawEntry::Vacant(RawVacantEntry {
+                map: self.map,
+                hash,
+                path,
+                target,
+            }),
+        }
+    }
+}
+
+impl<K: Eq, V, S: Clone, const B: usize> RawEntry<'_, K, V, S, B> {
+    #[must_use]
+    pub fn or_insert(self, key: K, value: V) -> PerMap<K, V, S, B>
+    where
+        K: Hash,
+        S: BuildHasher,
+    {
+        self.or_insert_with(|| (key, value))
+    }
+
+    #[must_use]
+    pub fn or_insert_with<F>(self, default: F) -> PerMap<K, V, S, B>
+    where
+        K: Hash,
+        S: BuildHasher,
+        F: FnOnce() -> (K, V),
+    {
+        match self {
+            RawEntry::Occupied(entry) => entry.map.with_data(Arc::clone(&entry.map.data)),
+            RawEntry::Vacant(entry) => {
+                let (key, value) = default();
+                entry.insert(key, value)
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn and_modify<F: FnOnce(&K, &V) -> V>(self, f: F) -> PerMap<K, V, S, B>
+    where
+        K: Clone,
+    {
+        match self {
+            RawEntry::Occupied(entry) => {
+                let (key, value) = entry.get_key_value();
+                let value = f(key, value);
+                entry.insert(value)
+            }
+            RawEntry::Vacant(entry) => entry.map.with_data(Arc::clone(&entry.map.data)),
+        }
+    }
+}
+
+impl<'a, K, V, S: Clone, const B: usize> RawOccupiedEntry<'a, K, V, S, B> {
+    #[must_use]
+    pub fn key(&self) -> &'a K {
+        &self.target.get().0
+    }
+
+    #[must_use]
+    pub fn get(&self) -> &'a V {
+        &self.target.get().1
+    }
+
+    #[must_use]
+    pub fn get_key_value(&self) -> (&'a K, &'a V) {
+        let entry = self.target.get();
+        (&entry.0, &entry.1)
+    }
+
+    /// Replaces the value and keeps the key that is already stored.
+    #[must_use]
+    pub fn insert(self, value: V) -> PerMap<K, V, S, B>
+    where
+        K: Clone,
+    {
+        let key = self.key().clone();
+        self.insert_key_value(key, value)
+    }
+
+    /// Replaces the whole entry. The new key has to be equal to the old one.
+    #[must_use]
+    pub fn insert_key_value(self, key: K, value: V) -> PerMap<K, V, S, B> {
+        let node = self.target.replace(key, value);
+        self.map.with_data(self.path.rebuild(node))
+    }
+
+    #[must_use]
+    pub fn remove(self) -> PerMap<K, V, S, B> {
+        let node = self.target.remove();
+        self.map.with_data(self.path.rebuild(node))
+    }
+}
+
+impl<K: Eq, V, S: Clone, const B: usize> RawVacantEntry<'_, K, V, S, B> {
+    #[must_use]
+    pub fn insert(self, key: K, value: V) -> PerMap<K, V, S, B>
+    where
+        K: Hash,
+        S: BuildHasher,
+    {
+        let hash = self.map.hasher.hash_one(&key);
+        self.insert_hashed_nocheck(hash, key, value)
+    }
+
+    /// Inserts the entry under `hash`, which is trusted to be the hash of
+    /// `key`. The path found by the lookup is reused when it is the same hash.
+    #[must_use]
+    pub fn insert_hashed_nocheck(self, hash: u64, key: K, value: V) -> PerMap<K, V, S, B> {
+        if hash != self.hash {
+            return self.map.insert_with_hash(hash, key, value);
+        }
+        let node = self.target.insert(key, value);
+        self.map.with_data(self.path.rebuild(node))
+    }
+}
@@ -0,0 +1,90 @@
+use std::{
+    fmt::{self, Formatter},
+    hash::{BuildHasher, Hash},
+    marker::PhantomData,
+};
+
+use serde::{
+    de::{MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{PerMap, PerMapBuilder, PerSet};
+
+impl<K: Serialize, V: Serialize, S, const B: usize> Serialize for PerMap<K, V, S, B> {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.collect_map(self.iter().map(|entry| (&entry.0, &entry.1)))
+    }
+}
+
+impl<K: Serialize, S, const B: usize> Serialize for PerSet<K, S, B> {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.collect_seq(self.0.keys())
+    }
+}
+
+impl<'de, K, V, S, const B: usize> Deserialize<'de> for PerMap<K, V, S, B>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    V: Deserialize<'de>,
+    S: BuildHasher + Clone + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(MapVisitor(PhantomData))
+    }
+}
+
+impl<'de, K, S, const B: usize> Deserialize<'de> for PerSet<K, S, B>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    S: BuildHasher + Clone + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(SetVisitor(PhantomData))
+    }
+}
+
+struct MapVisitor<K, V, S, const B: usize>(PhantomData<(K, V, S)>);
+
+impl<'de, K, V, S, const B: usize> Visitor<'de> for MapVisitor<K, V, S, B>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    V: Deserialize<'de>,
+    S: BuildHasher + Clone + Default,
+{
+    type Value = PerMap<K, V, S, B>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("a map")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut builder = PerMapBuilder::with_hasher(S::default());
+        while let Some((key, value)) = access.next_entry()? {
+            builder.insert(key, value);
+        }
+        Ok(builder.build())
+    }
+}
+
+struct SetVisitor<K, S, const B: usize>(PhantomData<(K, S)>);
+
+impl<'de, K, S, const B: usize> Visitor<'de> for SetVisitor<K, S, B>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    S: BuildHasher + Clone + Default,
+{
+    type Value = PerSet<K, S, B>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("a sequence")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut builder = PerMapBuilder::with_hasher(S::default());
+        while let Some(key) = access.next_element()? {
+            builder.insert(key, ());
+        }
+        Ok(PerSet(builder.build()))
+    }
+}
@@ -1,6 +1,6 @@
 use std::{
     borrow::Borrow,
-    hash::{BuildHasher, Hash},
+    hash::{BuildHasher, Hash, Hasher},
     ops::Deref,
     sync::Arc,
 };
@@ -10,7 +10,7 @@ use rustc_hash::FxBuildHasher;
 use crate::{iter, PerMap};
 
 #[derive(Debug, Clone)]
-pub struct PerSet<K, S = FxBuildHasher>(PerMap<K, (), S>);
+pub struct PerSet<K, S = FxBuildHasher, const B: usize = 16>(pub(crate) PerMap<K, (), S, B>);
 
 impl<K> PerSet<K> {
     #[must_use]
@@ -19,13 +19,13 @@ impl<K> PerSet<K> {
     }
 }
 
-impl<K> Default for PerSet<K> {
+impl<K, const B: usize> Default for PerSet<K, FxBuildHasher, B> {
     fn default() -> Self {
-        PerSet::<K>::empty()
+        Self::with_hasher(FxBuildHasher)
     }
 }
 
-impl<K, S> PerSet<K, S> {
+impl<K, S, const B: usize> PerSet<K, S, B> {
     #[must_use]
     pub fn with_hasher(hash_builder: S) -> Self {
         PerSet(PerMap::with_hasher(hash_builder))
@@ -38,9 +38,13 @@ impl<K, S> PerSet<K, S> {
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
+
+    pub fn iter(&self) -> Iter<'_, K, B> {
+        Iter(self.0.iter())
+    }
 }
 
-impl<K, S> PerSet<K, S>
+impl<K, S, const B: usize> PerSet<K, S, B>
 where
     K: Eq + Hash,
     S: BuildHasher + Clone,
@@ -50,22 +54,60 @@ where
         PerSet(self.0.insert(key, ()))
     }
 
+    #[must_use]
+    pub fn remove<Q>(&self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        PerSet(self.0.remove(key))
+    }
+
     #[must_use]
     pub fn contains<Q>(&self, key: &Q) -> bool
     where
         K: Borrow<Q>,
-        Q: Eq + Hash,
+        Q: Eq + Hash + ?Sized,
     {
         self.0.get(key).is_some()
     }
 
     #[must_use]
-    pub fn union(&self, other: &PerSet<K, S>) -> Self {
+    pub fn union(&self, other: &PerSet<K, S, B>) -> Self {
         PerSet(self.0.union(&other.0))
     }
+
+    #[must_use]
+    pub fn intersection(&self, other: &PerSet<K, S, B>) -> Self {
+        PerSet(self.0.intersection(&other.0))
+    }
+
+    #[must_use]
+    pub fn difference(&self, other: &PerSet<K, S, B>) -> Self {
+        PerSet(self.0.difference(&other.0))
+    }
+
+    #[must_use]
+    pub fn symmetric_difference(&self, other: &PerSet<K, S, B>) -> Self {
+        PerSet(self.0.symmetric_difference(&other.0))
+    }
+}
+
+impl<K: Eq, S, const B: usize> PartialEq for PerSet<K, S, B> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<K: Eq, S, const B: usize> Eq for PerSet<K, S, B> {}
+
+impl<K: Hash, S: BuildHasher, const B: usize> Hash for PerSet<K, S, B> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
 }
 
-pub struct Element<'a, K>(&'a Arc<(K, ())>);
+pub struct Element<'a, K>(pub(crate) &'a Arc<(K, ())>);
 
 impl<T> Deref for Element<'_, T> {
     type Target = T;
@@ -75,12 +117,54 @@ impl<T> Deref for Element<'_, T> {
     }
 }
 
-pub struct Iter<'a, K>(pub(crate) iter::Iter<'a, K, ()>);
+pub struct Iter<'a, K, const B: usize = 16>(pub(crate) iter::Iter<'a, K, (), B>);
 
-impl<'a, T> Iterator for Iter<'a, T> {
+impl<'a, T, const B: usize> Iterator for Iter<'a, T, B> {
     type Item = Element<'a, T>;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.0.next().map(Element)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<T, const B: usize> ExactSizeIterator for Iter<'_, T, B> {}
+
+impl<'a, K, S, const B: usize> IntoIterator for &'a PerSet<K, S, B> {
+    type Item = Element<'a, K>;
+
+    type IntoIter = Iter<'a, K, B>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct IntoIter<K, const B: usize = 16>(iter::IntoIter<K, (), B>);
+
+impl<K: Clone, const B: usize> Iterator for IntoIter<K, B> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(key, ())| key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K: Clone, const B: usize> ExactSizeIterator for IntoIter<K, B> {}
+
+impl<K: Clone, S, const B: usize> IntoIterator for PerSet<K, S, B> {
+    type Item = K;
+
+    type IntoIter = IntoIter<K, B>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self.0.into_iter())
+    }
 }
@@ -0,0 +1,421 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::{self, Display, Formatter},
+    hash::{BuildHasher, Hash},
+    iter,
+    sync::Arc,
+};
+
+use rustc_hash::FxBuildHasher;
+use smallvec::SmallVec;
+use sparse_vec::SparseVec;
+
+use crate::{nodes::Node, PerMap};
+
+const MAGIC: &[u8; 4] = b"PMS2";
+
+const LEAF: u8 = 0;
+
+const BRANCH: u8 = 1;
+
+pub trait Encode {
+    fn encode(&self, out: &mut Vec<u8>);
+}
+
+pub trait Decode: Sized {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    UnexpectedEnd,
+    InvalidMagic,
+    InvalidTag(u8),
+    InvalidBranching(usize),
+    InvalidReference(usize),
+    InvalidValue,
+    InvalidStructure,
+    HashMismatch,
+    TrailingBytes,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "snapshot ends unexpectedly"),
+            DecodeError::InvalidMagic => write!(f, "input is not a map snapshot"),
+            DecodeError::InvalidTag(tag) => write!(f, "unknown node tag {tag}"),
+            DecodeError::InvalidBranching(found) => {
+                write!(f, "snapshot was written with {found} children per branch")
+            }
+            DecodeError::InvalidReference(id) => write!(f, "reference to unknown node {id}"),
+            DecodeError::InvalidValue => write!(f, "value cannot be decoded"),
+            DecodeError::InvalidStructure => write!(f, "nodes do not form a valid trie"),
+            DecodeError::HashMismatch => {
+                write!(f, "stored hash does not match the hash of the key")
+            }
+            DecodeError::TrailingBytes => write!(f, "unexpected bytes after the last snapshot"),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+/// Writes any number of maps into a single buffer. Nodes are identified by
+/// address, so a subtree shared between snapshots is written once and later
+/// occurrences refer back to it.
+pub struct SnapshotEncoder<K, V, const B: usize = 16> {
+    body: Vec<u8>,
+    nodes: Vec<Arc<Node<K, V, B>>>,
+    ids: HashMap<*const Node<K, V, B>, usize, FxBuildHasher>,
+    roots: Vec<usize>,
+}
+
+impl<K, V, const B: usize> SnapshotEncoder<K, V, B> {
+    #[must_use]
+    pub fn new() -> Self {
+        SnapshotEncoder {
+            body: Vec::new(),
+            nodes: Vec::new(),
+            ids: HashMap::default(),
+            roots: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn finish(self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        write_len(B, &mut out);
+        write_len(self.nodes.len(), &mut out);
+        out.extend_from_slice(&self.body);
+        write_len(self.roots.len(), &mut out);
+        for root in self.roots {
+            write_len(root, &mut out);
+        }
+        out
+    }
+}
+
+impl<K, V, const B: usize> Default for SnapshotEncoder<K, V, B> {
+    fn default() -> Self {
+        SnapshotEncoder::new()
+    }
+}
+
+impl<K: Encode, V: Encode, const B: usize> SnapshotEncoder<K, V, B> {
+    pub fn add<S>(&mut self, map: &PerMap<K, V, S, B>) {
+        let root = self.visit(&map.data);
+        self.roots.push(root);
+    }
+
+    fn visit(&mut self, node: &Arc<Node<K, V, B>>) -> usize {
+        if let Some(id) = self.ids.get(&Arc::as_ptr(node)) {
+            return *id;
+        }
+        match &**node {
+            Node::Leaf { hash, data, .. } => {
+                self.body.push(LEAF);
+                hash.encode(&mut self.body);
+                write_len(data.len(), &mut self.body);
+                for entry in data {
+                    entry.0.encode(&mut self.body);
+                    entry.1.encode(&mut self.body);
+                }
+            }
+            Node::Branch { data, .. } => {
+                let children = data
+                    .iter()
+                    .map(|child| self.visit(child))
+                    .collect::<Vec<_>>();
+                self.body.push(BRANCH);
+                let mask = data.keys().iter().fold(0u64, |mask, key| mask | 1 << key);
+                self.body
+                    .extend_from_slice(&mask.to_le_bytes()[..mask_len::<B>()]);
+                for child in children {
+                    write_len(child, &mut self.body);
+                }
+            }
+        }
+        let id = self.nodes.len();
+        self.nodes.push(Arc::clone(node));
+        self.ids.insert(Arc::as_ptr(node), id);
+        id
+    }
+}
+
+pub fn encode<'a, K, V, S, I, const B: usize>(maps: I) -> Vec<u8>
+where
+    K: Encode + 'a,
+    V: Encode + 'a,
+    S: 'a,
+    I: IntoIterator<Item = &'a PerMap<K, V, S, B>>,
+{
+    let mut encoder = SnapshotEncoder::new();
+    for map in maps {
+        encoder.add(map);
+    }
+    encoder.finish()
+}
+
+/// Decodes maps with a hasher made by `S::default()`. Every stored hash is
+/// checked against it, so this only works for hashers whose default is
+/// deterministic; use [`decode_with_hasher`] for randomly seeded ones such as
+/// `RandomState`.
+pub fn decode<K, V, S, const B: usize>(input: &[u8]) -> Result<Vec<PerMap<K, V, S, B>>, DecodeError>
+where
+    K: Decode + Eq + Hash,
+    V: Decode,
+    S: BuildHasher + Clone + Default,
+{
+    decode_with_hasher(input, S::default())
+}
+
+/// Decodes maps that were built with `hasher`, or with one hashing the same
+/// way.
+pub fn decode_with_hasher<K, V, S, const B: usize>(
+    mut input: &[u8],
+    hasher: S,
+) -> Result<Vec<PerMap<K, V, S, B>>, DecodeError>
+where
+    K: Decode + Eq + Hash,
+    V: Decode,
+    S: BuildHasher + Clone,
+{
+    if take(&mut input, MAGIC.len())? != MAGIC {
+        return Err(DecodeError::InvalidMagic);
+    }
+    let branching = read_len(&mut input)?;
+    if branching != B {
+        return Err(DecodeError::InvalidBranching(branching));
+    }
+    let node_count = read_len(&mut input)?;
+    let mut nodes: Vec<Arc<Node<K, V, B>>> = Vec::new();
+    for _ in 0..node_count {
+        let node = match u8::decode(&mut input)? {
+            LEAF => {
+                let hash = u64::decode(&mut input)?;
+                let len = read_len(&mut input)?;
+                let mut data = SmallVec::new();
+                for _ in 0..len {
+                    let key = K::decode(&mut input)?;
+                    if hasher.hash_one(&key) != hash {
+                        return Err(DecodeError::HashMismatch);
+                    }
+                    if data.iter().any(|entry: &Arc<(K, V)>| entry.0 == key) {
+                        return Err(DecodeError::InvalidStructure);
+                    }
+                    data.push(Arc::new((key, V::decode(&mut input)?)));
+                }
+                Node::leaf(hash, data)
+            }
+            BRANCH => {
+                let mut mask = [0; 8];
+                mask[..mask_len::<B>()].copy_from_slice(take(&mut input, mask_len::<B>())?);
+                let mask = u64::from_le_bytes(mask);
+                if B < 64 && mask >> B != 0 {
+                    return Err(DecodeError::InvalidStructure);
+                }
+                let mut data = SparseVec::new();
+                for key in (0..B).filter(|key| mask & 1 << key != 0) {
+                    let id = read_len(&mut input)?;
+                    let child = nodes.get(id).ok_or(DecodeError::InvalidReference(id))?;
+                    data.insert(key, Arc::clone(child));
+                }
+                // Ids can be reused, so a few bytes can describe a tree whose
+                // weight does not fit, long before the shape is validated.
+                let weight = data
+                    .iter()
+                    .try_fold(0usize, |weight, child| weight.checked_add(child.weight()))
+                    .ok_or(DecodeError::InvalidStructure)?;
+                Node::Branch { data, weight }
+            }
+            tag => return Err(DecodeError::InvalidTag(tag)),
+        };
+        nodes.push(Arc::new(node));
+    }
+    let root_count = read_len(&mut input)?;
+    let mut roots = Vec::new();
+    let mut checked = HashSet::default();
+    for _ in 0..root_count {
+        let id = read_len(&mut input)?;
+        let root = nodes.get(id).ok_or(DecodeError::InvalidReference(id))?;
+        if let Node::Leaf { .. } = **root {
+            return Err(DecodeError::InvalidStructure);
+        }
+        validate(root, 0, 0, &mut checked)?;
+        roots.push(Arc::clone(root));
+    }
+    if !input.is_empty() {
+        return Err(DecodeError::TrailingBytes);
+    }
+    let hashers = iter::repeat_n(hasher, roots.len());
+    Ok(roots
+        .into_iter()
+        .zip(hashers)
+        .map(|(data, hasher)| PerMap { data, hasher })
+        .collect())
+}
+
+/// Checks that every leaf sits under the hash prefix leading to it and that
+/// branches below the root are never empty or hold just a single leaf. A
+/// shared node is checked once for every position it appears at.
+fn validate<K, V, const B: usize>(
+    node: &Arc<Node<K, V, B>>,
+    shift: usize,
+    prefix: u64,
+    checked: &mut HashSet<(*const Node<K, V, B>, usize, u64), FxBuildHasher>,
+) -> Result<(), DecodeError> {
+    if !checked.insert((Arc::as_ptr(node), shift, prefix)) {
+        return Ok(());
+    }
+    match &**node {
+        Node::Leaf { hash, data, .. } => {
+            let mask = 1u64
+                .checked_shl(shift as u32)
+                .map_or(u64::MAX, |bit| bit - 1);
+            if data.is_empty() || hash & mask != prefix {
+                return Err(DecodeError::InvalidStructure);
+            }
+        }
+        Node::Branch { data, .. } => {
+            let single_leaf =
+                data.len() == 1 && data.iter().all(|n| matches!(**n, Node::Leaf { .. }));
+            if shift >= 64 || (shift > 0 && (data.is_empty() || single_leaf)) {
+                return Err(DecodeError::InvalidStructure);
+            }
+            for key in data.keys() {
+                let child = data.get(key).unwrap();
+                validate(
+                    child,
+                    shift + Node::<K, V, B>::BITS,
+                    prefix | (key as u64) << shift,
+                    checked,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Bytes needed for the mask of present children in a branch.
+fn mask_len<const B: usize>() -> usize {
+    B.div_ceil(8)
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], DecodeError> {
+    if input.len() < len {
+        return Err(DecodeError::UnexpectedEnd);
+    }
+    let (head, tail) = input.split_at(len);
+    *input = tail;
+    Ok(head)
+}
+
+fn write_len(mut len: usize, out: &mut Vec<u8>) {
+    while len >= 0x80 {
+        out.push((len as u8) | 0x80);
+        len >>= 7;
+    }
+    out.push(len as u8);
+}
+
+fn read_len(input: &mut &[u8]) -> Result<usize, DecodeError> {
+    let mut len = 0usize;
+    for shift in (0..usize::BITS).step_by(7) {
+        let byte = u8::decode(input)?;
+        len |= usize::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(len);
+        }
+    }
+    Err(DecodeError::InvalidValue)
+}
+
+macro_rules! fixed_width {
+    ($($ty:ty),*) => {$(
+        impl Encode for $ty {
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+        }
+
+        impl Decode for $ty {
+            fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+                let bytes = take(input, size_of::<$ty>())?;
+                Ok(<$ty>::from_le_bytes(bytes.try_into().unwrap()))
+            }
+        }
+    )*};
+}
+
+fixed_width!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl Encode for usize {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_len(*self, out);
+    }
+}
+
+impl Decode for usize {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        read_len(input)
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(u8::from(*self));
+    }
+}
+
+impl Decode for bool {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        match u8::decode(input)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DecodeError::InvalidValue),
+        }
+    }
+}
+
+impl Encode for () {
+    fn encode(&self, _: &mut Vec<u8>) {}
+}
+
+impl Decode for () {
+    fn decode(_: &mut &[u8]) -> Result<Self, DecodeError> {
+        Ok(())
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_len(self.len(), out);
+        out.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Decode for String {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let len = read_len(input)?;
+        let bytes = take(input, len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::InvalidValue)
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_len(self.len(), out);
+        for elem in self {
+            elem.encode(out);
+        }
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodeError> {
+        let len = read_len(input)?;
+        (0..len).map(|_| T::decode(input)).collect()
+    }
+}
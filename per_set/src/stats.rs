@@ -0,0 +1,186 @@
+use std::{collections::HashMap, sync::Arc};
+
+use rustc_hash::FxBuildHasher;
+
+use crate::{nodes::Node, PerMap, PerSet};
+
+/// Shape and memory figures for one or more maps. Byte counts cover the trie
+/// nodes and entry allocations, but not memory owned by the keys and values.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub branches: usize,
+    pub leaves: usize,
+    pub entries: usize,
+    /// `depths[d]` is the number of leaves `d` levels below the root.
+    pub depths: Vec<usize>,
+    /// `leaf_sizes[n]` is the number of leaves holding `n` entries.
+    pub leaf_sizes: Vec<usize>,
+    /// `fill[n]` is the number of branches with `n` children.
+    pub fill: Vec<usize>,
+    /// Child slots over all branches, used or not.
+    pub slots: usize,
+    pub bytes: usize,
+    /// Nodes reachable from more than one of the measured maps.
+    pub shared_nodes: usize,
+    /// Bytes of nodes and entries reachable from more than one of the
+    /// measured maps.
+    pub shared_bytes: usize,
+}
+
+impl Stats {
+    #[must_use]
+    pub fn nodes(&self) -> usize {
+        self.branches + self.leaves
+    }
+
+    #[must_use]
+    pub fn unique_bytes(&self) -> usize {
+        self.bytes - self.shared_bytes
+    }
+
+    /// The share of child slots in use, over all branches.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn fill_ratio(&self) -> f64 {
+        if self.slots == 0 {
+            return 0.0;
+        }
+        let used = self
+            .fill
+            .iter()
+            .enumerate()
+            .map(|(n, b)| n * b)
+            .sum::<usize>();
+        used as f64 / self.slots as f64
+    }
+}
+
+enum Visit {
+    New,
+    Shared,
+    Skip,
+}
+
+/// Measures any number of maps together. Allocations are identified by
+/// address, so whatever several maps share is counted once and reported as
+/// shared.
+#[derive(Default)]
+pub struct StatsCollector {
+    stats: Stats,
+    seen: HashMap<*const (), (usize, bool), FxBuildHasher>,
+    maps: usize,
+}
+
+impl StatsCollector {
+    #[must_use]
+    pub fn new() -> Self {
+        StatsCollector::default()
+    }
+
+    pub fn add<K, V, S, const B: usize>(&mut self, map: &PerMap<K, V, S, B>) {
+        self.visit(&map.data, 0);
+        self.maps += 1;
+    }
+
+    pub fn add_set<K, S, const B: usize>(&mut self, set: &PerSet<K, S, B>) {
+        self.add(&set.0);
+    }
+
+    #[must_use]
+    pub fn finish(self) -> Stats {
+        self.stats
+    }
+
+    /// Every allocation remembers the last map it was reached from and whether
+    /// an earlier map reached it too. Below a node already known to be shared
+    /// everything is shared as well, so such subtrees are not walked again.
+    fn mark(&mut self, ptr: *const (), bytes: usize) -> Visit {
+        match self.seen.get_mut(&ptr) {
+            None => {
+                self.seen.insert(ptr, (self.maps, false));
+                self.stats.bytes += bytes;
+                Visit::New
+            }
+            Some((map, shared)) if *map == self.maps || *shared => Visit::Skip,
+            Some((map, shared)) => {
+                *map = self.maps;
+                *shared = true;
+                self.stats.shared_bytes += bytes;
+                Visit::Shared
+            }
+        }
+    }
+
+    fn visit<K, V, const B: usize>(&mut self, node: &Arc<Node<K, V, B>>, depth: usize) {
+        let bytes = allocation::<Node<K, V, B>>()
+            + match &**node {
+                Node::Leaf { data, .. } if data.spilled() => {
+                    data.capacity() * size_of::<Arc<(K, V)>>()
+                }
+                Node::Branch { data, .. } if data.spilled() => {
+                    data.capacity() * size_of::<Arc<Node<K, V, B>>>()
+                }
+                _ => 0,
+            };
+        let visit = self.mark(Arc::as_ptr(node).cast(), bytes);
+        match visit {
+            Visit::Skip => return,
+            Visit::Shared => self.stats.shared_nodes += 1,
+            Visit::New => match &**node {
+                Node::Leaf { data, .. } => {
+                    self.stats.leaves += 1;
+                    bump(&mut self.stats.depths, depth);
+                    bump(&mut self.stats.leaf_sizes, data.len());
+                }
+                Node::Branch { data, .. } => {
+                    self.stats.branches += 1;
+                    self.stats.slots += B;
+                    bump(&mut self.stats.fill, data.len());
+                }
+            },
+        }
+        match &**node {
+            Node::Leaf { data, .. } => {
+                for entry in data {
+                    let visit = self.mark(Arc::as_ptr(entry).cast(), allocation::<(K, V)>());
+                    if let Visit::New = visit {
+                        self.stats.entries += 1;
+                    }
+                }
+            }
+            Node::Branch { data, .. } => {
+                for child in data {
+                    self.visit(child, depth + 1);
+                }
+            }
+        }
+    }
+}
+
+/// Size of an `Arc` allocation, including the two reference counts.
+fn allocation<T>() -> usize {
+    2 * size_of::<usize>() + size_of::<T>()
+}
+
+fn bump(histogram: &mut Vec<usize>, at: usize) {
+    if histogram.len() <= at {
+        histogram.resize(at + 1, 0);
+    }
+    histogram[at] += 1;
+}
+
+impl<K, V, S, const B: usize> PerMap<K, V, S, B> {
+    #[must_use]
+    pub fn stats(&self) -> Stats {
+        let mut collector = StatsCollector::new();
+        collector.add(self);
+        collector.finish()
+    }
+}
+
+impl<K, S, const B: usize> PerSet<K, S, B> {
+    #[must_use]
+    pub fn stats(&self) -> Stats {
+        self.0.stats()
+    }
+}
@@ -1,17 +1,20 @@
 use std::{
     borrow::Borrow,
-    hash::{BuildHasher, Hash},
+    hash::{BuildHasher, Hash, Hasher},
     sync::Arc,
 };
 
 use rustc_hash::FxBuildHasher;
 
-use crate::nodes::{BitShifter, Node};
+use crate::{
+    entry::Entry,
+    nodes::{BitShifter, Node, ResolveWith, TakeRight, Target},
+};
 
 #[derive(Clone, Debug)]
-pub struct PerMap<K, V, S = FxBuildHasher> {
-    pub(crate) data: Arc<Node<K, V>>,
-    hasher: S,
+pub struct PerMap<K, V, S = FxBuildHasher, const B: usize = 16> {
+    pub(crate) data: Arc<Node<K, V, B>>,
+    pub(crate) hasher: S,
 }
 
 impl<K, V> PerMap<K, V, FxBuildHasher> {
@@ -21,13 +24,13 @@ impl<K, V> PerMap<K, V, FxBuildHasher> {
     }
 }
 
-impl<K, V> Default for PerMap<K, V> {
+impl<K, V, const B: usize> Default for PerMap<K, V, FxBuildHasher, B> {
     fn default() -> Self {
-        PerMap::<K, V>::empty()
+        Self::with_hasher(FxBuildHasher)
     }
 }
 
-impl<K, V, S> PerMap<K, V, S> {
+impl<K, V, S, const B: usize> PerMap<K, V, S, B> {
     pub fn with_hasher(hash_builder: S) -> Self {
         Self {
             data: Arc::default(),
@@ -43,24 +46,90 @@ impl<K, V, S> PerMap<K, V, S> {
         self.data.weight() == 0
     }
 
-    pub fn iter(&self) -> crate::iter::Iter<'_, K, V> {
+    pub fn iter(&self) -> crate::iter::Iter<'_, K, V, B> {
         crate::iter::Iter::new(self)
     }
+
+    pub fn keys(&self) -> crate::iter::Keys<'_, K, V, B> {
+        crate::iter::Keys(self.iter())
+    }
+
+    pub fn values(&self) -> crate::iter::Values<'_, K, V, B> {
+        crate::iter::Values(self.iter())
+    }
+
+    pub(crate) fn with_data(&self, data: Arc<Node<K, V, B>>) -> Self
+    where
+        S: Clone,
+    {
+        Self {
+            data,
+            hasher: self.hasher.clone(),
+        }
+    }
+}
+
+impl<K, V, S: Clone, const B: usize> PerMap<K, V, S, B> {
+    #[must_use]
+    pub fn filter<F: FnMut(&K, &V) -> bool>(&self, mut keep: F) -> Self {
+        self.with_data(Node::filter(&self.data, &mut keep, 0))
+    }
+
+    pub fn retain<F: FnMut(&K, &V) -> bool>(&mut self, mut keep: F) {
+        self.data = Node::filter(&self.data, &mut keep, 0);
+    }
+
+    /// Returns the entries accepted by `pred` and the remaining ones.
+    #[must_use]
+    pub fn partition<F: FnMut(&K, &V) -> bool>(&self, mut pred: F) -> (Self, Self) {
+        let [accepted, rejected] = Node::partition(&self.data, &mut pred, 0);
+        (self.with_data(accepted), self.with_data(rejected))
+    }
+
+    #[must_use]
+    pub fn map_values<W, F: FnMut(&K, &V) -> W>(&self, mut f: F) -> PerMap<K, W, S, B>
+    where
+        K: Clone,
+    {
+        self.filter_map(|key, value| Some(f(key, value)))
+    }
+
+    #[must_use]
+    pub fn filter_map<W, F: FnMut(&K, &V) -> Option<W>>(&self, mut f: F) -> PerMap<K, W, S, B>
+    where
+        K: Clone,
+    {
+        PerMap {
+            data: Node::filter_map(&self.data, &mut f, 0),
+            hasher: self.hasher.clone(),
+        }
+    }
 }
 
-impl<K, V, S> PerMap<K, V, S>
+impl<K, V, S, const B: usize> PerMap<K, V, S, B>
 where
     K: Eq + Hash,
     S: BuildHasher + Clone,
 {
     #[must_use]
     pub fn insert(&self, key: K, value: V) -> Self {
-        let hash = self.hasher.hash_one(&key);
-        let address = BitShifter::new(hash);
-        let new_data = Node::insert(&self.data, key, value, address);
-        Self {
-            data: new_data,
-            hasher: self.hasher.clone(),
+        self.entry(key).insert(value)
+    }
+
+    #[must_use]
+    pub fn update<F>(&self, key: K, f: F) -> Self
+    where
+        F: FnOnce(Option<&V>) -> Option<V>,
+    {
+        match self.entry(key) {
+            Entry::Occupied(entry) => match f(Some(entry.get())) {
+                Some(value) => entry.insert(value),
+                None => entry.remove(),
+            },
+            Entry::Vacant(entry) => match f(None) {
+                Some(value) => entry.insert(value),
+                None => self.with_data(Arc::clone(&self.data)),
+            },
         }
     }
 
@@ -68,29 +137,216 @@ where
     pub fn get<Q>(&self, key: &Q) -> Option<&V>
     where
         K: Borrow<Q>,
-        Q: Eq + Hash,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.get_key_value(key).map(|(_, value)| value)
+    }
+
+    #[must_use]
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let hash = self.hasher.hash_one(key);
+        self.get_key_value_with_hash(hash, key)
+    }
+
+    #[must_use]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.get_key_value(key).is_some()
+    }
+
+    #[must_use]
+    pub fn remove<Q>(&self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
     {
         let hash = self.hasher.hash_one(key);
         let address = BitShifter::new(hash);
-        let res = self.data.get(key, address);
-        res
+        match Node::find(&self.data, address, |k| k.borrow() == key) {
+            (path, Target::Found(target)) => self.with_data(path.rebuild(target.remove())),
+            (_, Target::Missing(_)) => self.with_data(Arc::clone(&self.data)),
+        }
+    }
+
+    /// Looks `key` up once and rebuilds its leaf from what `f` makes of the
+    /// value: `None` keeps the map as it is, `Some(None)` removes the entry
+    /// and `Some(Some(value))` replaces the value under the stored key.
+    pub(crate) fn modify<Q, F>(&self, key: &Q, f: F) -> Self
+    where
+        K: Borrow<Q> + Clone,
+        Q: Eq + Hash + ?Sized,
+        F: FnOnce(&V) -> Option<Option<V>>,
+    {
+        let address = BitShifter::new(self.hasher.hash_one(key));
+        if let (path, Target::Found(target)) =
+            Node::find(&self.data, address, |k| k.borrow() == key)
+        {
+            let (key, value) = &**target.get();
+            match f(value) {
+                Some(Some(value)) => {
+                    return self.with_data(path.rebuild(target.replace(key.clone(), value)));
+                }
+                Some(None) => return self.with_data(path.rebuild(target.remove())),
+                None => {}
+            }
+        }
+        self.with_data(Arc::clone(&self.data))
+    }
+
+    #[must_use]
+    pub fn union(&self, other: &PerMap<K, V, S, B>) -> Self {
+        PerMap {
+            data: Node::merge(&self.data, &other.data, &TakeRight, 0),
+            hasher: self.hasher.clone(),
+        }
+    }
+
+    #[must_use]
+    pub fn union_with<F>(&self, other: &PerMap<K, V, S, B>, f: F) -> Self
+    where
+        K: Clone,
+        F: Fn(&K, &V, &V) -> V,
+    {
+        PerMap {
+            data: Node::merge(&self.data, &other.data, &ResolveWith(f), 0),
+            hasher: self.hasher.clone(),
+        }
+    }
+
+    #[must_use]
+    pub fn intersection(&self, other: &PerMap<K, V, S, B>) -> Self {
+        PerMap {
+            data: Node::intersection(&self.data, &other.data, &TakeRight, 0),
+            hasher: self.hasher.clone(),
+        }
+    }
+
+    #[must_use]
+    pub fn intersection_with<F>(&self, other: &PerMap<K, V, S, B>, f: F) -> Self
+    where
+        K: Clone,
+        F: Fn(&K, &V, &V) -> V,
+    {
+        PerMap {
+            data: Node::intersection(&self.data, &other.data, &ResolveWith(f), 0),
+            hasher: self.hasher.clone(),
+        }
+    }
+
+    #[must_use]
+    pub fn difference(&self, other: &PerMap<K, V, S, B>) -> Self {
+        PerMap {
+            data: Node::difference(&self.data, &other.data, 0),
+            hasher: self.hasher.clone(),
+        }
     }
 
     #[must_use]
-    pub fn union(&self, other: &PerMap<K, V, S>) -> Self {
+    pub fn symmetric_difference(&self, other: &PerMap<K, V, S, B>) -> Self {
         PerMap {
-            data: Node::merge(&self.data, &other.data),
+            data: Node::symmetric_difference(&self.data, &other.data, 0),
             hasher: self.hasher.clone(),
         }
     }
 }
 
-impl<'a, K, V, S> IntoIterator for &'a PerMap<K, V, S> {
+/// Lookups and insertions that take a hash computed ahead of time instead of
+/// hashing the key again. The hash must be the one this map's hasher produces
+/// for the key; a different one sends the operation to the wrong part of the
+/// trie, so lookups miss and insertions leave entries that only the same
+/// wrong hash can reach.
+impl<K: Eq, V, S, const B: usize> PerMap<K, V, S, B> {
+    #[must_use]
+    pub fn get_with_hash<Q>(&self, hash: u64, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.get_key_value_with_hash(hash, key)
+            .map(|(_, value)| value)
+    }
+
+    #[must_use]
+    pub fn get_key_value_with_hash<Q>(&self, hash: u64, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.data
+            .get(BitShifter::new(hash), |k| k.borrow() == key)
+            .map(|entry| (&entry.0, &entry.1))
+    }
+
+    #[must_use]
+    pub fn contains_key_with_hash<Q>(&self, hash: u64, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.get_key_value_with_hash(hash, key).is_some()
+    }
+
+    #[must_use]
+    pub fn insert_with_hash(&self, hash: u64, key: K, value: V) -> Self
+    where
+        S: Clone,
+    {
+        let node = match Node::find(&self.data, BitShifter::new(hash), |k| *k == key) {
+            (path, Target::Found(target)) => path.rebuild(target.replace(key, value)),
+            (path, Target::Missing(target)) => path.rebuild(target.insert(key, value)),
+        };
+        self.with_data(node)
+    }
+}
+
+impl<K: Eq, V: PartialEq, S, const B: usize> PerMap<K, V, S, B> {
+    pub fn diff<'a>(&'a self, other: &'a PerMap<K, V, S, B>) -> crate::diff::Diff<'a, K, V, B> {
+        crate::diff::Diff::new(&self.data, &other.data)
+    }
+}
+
+impl<K: Eq, V: PartialEq, S, const B: usize> PartialEq for PerMap<K, V, S, B> {
+    fn eq(&self, other: &Self) -> bool {
+        Node::equal(&self.data, &other.data)
+    }
+}
+
+impl<K: Eq, V: Eq, S, const B: usize> Eq for PerMap<K, V, S, B> {}
+
+impl<K: Hash, V: Hash, S: BuildHasher, const B: usize> Hash for PerMap<K, V, S, B> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let entries = self
+            .iter()
+            .map(|entry| self.hasher.hash_one(&**entry))
+            .fold(0u64, u64::wrapping_add);
+        state.write_usize(self.len());
+        state.write_u64(entries);
+    }
+}
+
+impl<'a, K, V, S, const B: usize> IntoIterator for &'a PerMap<K, V, S, B> {
     type Item = &'a Arc<(K, V)>;
 
-    type IntoIter = crate::iter::Iter<'a, K, V>;
+    type IntoIter = crate::iter::Iter<'a, K, V, B>;
 
     fn into_iter(self) -> Self::IntoIter {
         crate::iter::Iter::new(self)
     }
 }
+
+impl<K: Clone, V: Clone, S, const B: usize> IntoIterator for PerMap<K, V, S, B> {
+    type Item = (K, V);
+
+    type IntoIter = crate::iter::IntoIter<K, V, B>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        crate::iter::IntoIter::new(self)
+    }
+}
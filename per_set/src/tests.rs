@@ -1,10 +1,30 @@
 use std::{
-    collections::{HashMap, HashSet},
-    hash::{BuildHasher, Hasher},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    hash::{BuildHasher, Hasher, RandomState},
+    ops::{Bound, RangeBounds},
+    sync::Arc,
 };
 
-use super::PerMap;
-use proptest::{collection::hash_map, prelude::*};
+use super::{
+    diff::DiffItem,
+    dot,
+    entry::Entry,
+    history::Released,
+    nodes::Node,
+    ord_nodes::{self, Tree},
+    raw_entry::RawEntry,
+    snapshot::{self, DecodeError},
+    stats::StatsCollector,
+    vector_nodes, BiMapError, PerBag, PerBiMap, PerMap, PerMapBuilder, PerMapHistory, PerMultiMap,
+    PerOrdMap, PerOrdSet, PerSet, PerVec,
+};
+use proptest::{
+    collection::{hash_map, hash_set, vec},
+    prelude::*,
+};
+use rustc_hash::FxBuildHasher;
+use smallvec::smallvec;
+use sparse_vec::SparseVec;
 use test_utils::map_with_selected;
 
 fn configure() -> ProptestConfig {
@@ -52,6 +72,221 @@ proptest! {
         prop_assert_eq!(old_value, map.get(&selected));
     }
 
+    #[test]
+    fn values_can_be_removed_but_older_snapshots_remain((elems, selected) in map_with_selected(16, 0u64..1024)) {
+        let map = PerMap::<u64, String>::empty();
+
+        let map = elems.iter().fold(map, |m, (k, v)| m.insert(*k, v.clone()));
+
+        let new_map = map.remove(&selected);
+
+        prop_assert_eq!(None, new_map.get(&selected));
+        prop_assert_eq!(elems.len() - 1, new_map.len());
+        prop_assert_eq!(elems.get(&selected), map.get(&selected));
+        prop_assert_eq!(elems.len(), map.len());
+
+        for (k, v) in elems.iter().filter(|(k, _)| **k != selected) {
+            prop_assert_eq!(Some(v), new_map.get(k));
+        }
+    }
+
+    #[test]
+    fn removing_absent_key_keeps_map_intact(elems in hash_map(0u64..1024, "\\w{1,7}", 0usize..16)) {
+        let map = PerMap::<u64, String>::empty();
+        let map = elems.iter().fold(map, |m, (k, v)| m.insert(*k, v.clone()));
+
+        let new_map = map.remove(&2048);
+
+        prop_assert_eq!(elems.len(), new_map.len());
+        prop_assert!(Arc::ptr_eq(&map.data, &new_map.data));
+    }
+
+    #[test]
+    fn removing_all_keys_leaves_empty_map(elems in hash_map(0u64..1024, "\\w{1,7}", 0usize..16)) {
+        let map = PerMap::<u64, String>::empty();
+        let map = elems.iter().fold(map, |m, (k, v)| m.insert(*k, v.clone()));
+
+        let map = elems.keys().fold(map, |m, k| m.remove(k));
+
+        prop_assert!(map.is_empty());
+        prop_assert_eq!(0, map.iter().count());
+        match map.data.as_ref() {
+            Node::Branch { data, .. } => prop_assert!(data.is_empty()),
+            Node::Leaf { .. } => prop_assert!(false),
+        }
+    }
+
+    #[test]
+    fn equality_and_hash_do_not_depend_on_history(
+        elems in hash_map(0u64..1024, "\\w{1,7}", 0usize..16),
+        removed in hash_map(1024u64..2048, "\\w{1,7}", 0usize..4),
+    ) {
+        let mut sorted = elems.iter().collect::<Vec<_>>();
+        sorted.sort();
+
+        let forward = sorted.iter().fold(PerMap::<u64, String>::empty(), |m, (k, v)| m.insert(**k, (*v).clone()));
+        let backward = sorted.iter().rev()
+            .copied()
+            .chain(removed.iter())
+            .fold(PerMap::<u64, String>::empty(), |m, (k, v)| m.insert(*k, v.clone()));
+        let backward = removed.keys().fold(backward, |m, k| m.remove(k));
+
+        prop_assert!(forward == backward);
+        prop_assert_eq!(FxBuildHasher.hash_one(&forward), FxBuildHasher.hash_one(&backward));
+
+        let forward_set = elems.keys().fold(PerSet::<u64>::empty(), |s, k| s.insert(*k));
+        let backward_set = sorted.iter().rev().fold(PerSet::<u64>::empty(), |s, (k, _)| s.insert(**k));
+        prop_assert!(forward_set == backward_set);
+        prop_assert_eq!(FxBuildHasher.hash_one(&forward_set), FxBuildHasher.hash_one(&backward_set));
+    }
+
+    #[test]
+    fn maps_with_different_entries_are_not_equal((elems, selected) in map_with_selected(16, 0u64..1024)) {
+        let map = PerMap::<u64, String>::empty();
+        let map = elems.iter().fold(map, |m, (k, v)| m.insert(*k, v.clone()));
+
+        prop_assert!(map != map.insert(selected, "new value".to_string()));
+        prop_assert!(map != map.remove(&selected));
+        prop_assert!(map != map.insert(2048, "new value".to_string()));
+    }
+
+    #[test]
+    fn diff_reports_added_removed_and_changed_entries(
+        common in hash_map(0u64..1024, 0u64..4, 0usize..32),
+        removed in hash_set(0u64..1024, 0usize..8),
+        updated in hash_map(0u64..2048, 0u64..4, 0usize..8),
+    ) {
+        let old = common.iter().fold(PerMap::<u64, u64>::empty(), |m, (k, v)| m.insert(*k, *v));
+        let new = removed.iter().fold(old.clone(), |m, k| m.remove(k));
+        let new = updated.iter().fold(new, |m, (k, v)| m.insert(*k, *v));
+
+        let mut expected = common.iter()
+            .map(|(k, v)| (*k, (Some(*v), None)))
+            .collect::<HashMap<_, _>>();
+        for (k, v) in new.iter().map(|e| &**e) {
+            expected.entry(*k).or_insert((None, None)).1 = Some(*v);
+        }
+        expected.retain(|_, (o, n)| o != n);
+
+        let mut actual = HashMap::new();
+        for item in old.diff(&new) {
+            let (k, change) = match item {
+                DiffItem::Added(k, v) => (*k, (None, Some(*v))),
+                DiffItem::Removed(k, v) => (*k, (Some(*v), None)),
+                DiffItem::Changed { key, old, new } => {
+                    prop_assert_ne!(old, new);
+                    (*key, (Some(*old), Some(*new)))
+                }
+            };
+            prop_assert!(actual.insert(k, change).is_none());
+        }
+
+        prop_assert_eq!(expected, actual);
+        prop_assert_eq!(0, new.diff(&new).count());
+    }
+
+    #[test]
+    fn update_inserts_modifies_and_removes_in_one_call(
+        elems in hash_map(0u64..64, 0u64..1024, 0usize..16),
+        updates in hash_map(0u64..64, proptest::option::of(0u64..1024), 0usize..16),
+    ) {
+        let map = elems.iter().fold(PerMap::<u64, u64>::empty(), |m, (k, v)| m.insert(*k, *v));
+
+        let updated = updates.iter().fold(map.clone(), |m, (k, delta)| {
+            m.update(*k, |old| delta.map(|d| old.copied().unwrap_or_default() + d))
+        });
+
+        let mut expected = elems.clone();
+        for (k, delta) in &updates {
+            match delta {
+                Some(d) => *expected.entry(*k).or_default() += d,
+                None => {
+                    expected.remove(k);
+                }
+            }
+        }
+
+        prop_assert_eq!(expected.len(), updated.len());
+        for (k, v) in &expected {
+            prop_assert_eq!(Some(v), updated.get(k));
+        }
+        for (k, v) in &elems {
+            prop_assert_eq!(Some(v), map.get(k));
+        }
+    }
+
+    #[test]
+    fn entry_api_returns_original_root_when_nothing_changes((elems, selected) in map_with_selected(16, 0u64..1024)) {
+        let map = PerMap::<u64, String>::empty();
+        let map = elems.iter().fold(map, |m, (k, v)| m.insert(*k, v.clone()));
+
+        prop_assert!(Arc::ptr_eq(&map.data, &map.update(2048, |_| None).data));
+        prop_assert!(Arc::ptr_eq(&map.data, &map.entry(selected).or_insert("new value".to_string()).data));
+        prop_assert!(Arc::ptr_eq(&map.data, &map.entry(2048).and_modify(|_| "new value".to_string()).data));
+
+        match map.entry(selected) {
+            Entry::Occupied(entry) => {
+                prop_assert_eq!(elems.get(&selected), Some(entry.get()));
+                let removed = entry.remove();
+                prop_assert_eq!(None, removed.get(&selected));
+                prop_assert_eq!(elems.len() - 1, removed.len());
+            }
+            Entry::Vacant(_) => prop_assert!(false),
+        }
+
+        let modified = map.entry(selected).and_modify(|v| format!("{v}!"));
+        prop_assert_eq!(Some(&format!("{}!", elems[&selected])), modified.get(&selected));
+
+        let inserted = map.entry(2048).or_insert_with(|| "new value".to_string());
+        prop_assert_eq!(Some(&"new value".to_string()), inserted.get(&2048));
+        prop_assert_eq!(elems.len() + 1, inserted.len());
+    }
+
+    #[test]
+    fn bulk_construction_matches_persistent_inserts(
+        elems in hash_map(0u64..1024, "\\w{1,7}", 0usize..32),
+        extra in hash_map(0u64..2048, "\\w{1,7}", 0usize..16),
+    ) {
+        let inserted = elems.iter().fold(PerMap::<u64, String>::empty(), |m, (k, v)| m.insert(*k, v.clone()));
+        let collected = elems.iter().map(|(k, v)| (*k, v.clone())).collect::<PerMap<u64, String>>();
+        prop_assert!(inserted == collected);
+
+        let mut builder = PerMapBuilder::new();
+        builder.extend(elems.iter().map(|(k, v)| (*k, v.clone())));
+        prop_assert_eq!(elems.len(), builder.len());
+        prop_assert!(inserted == builder.build());
+
+        let mut extended = inserted.clone();
+        extended.extend(extra.iter().map(|(k, v)| (*k, v.clone())));
+        let expected = extra.iter().fold(inserted.clone(), |m, (k, v)| m.insert(*k, v.clone()));
+        prop_assert!(expected == extended);
+        prop_assert!(inserted == collected);
+
+        let set = elems.keys().copied().collect::<PerSet<u64>>();
+        prop_assert_eq!(elems.len(), set.len());
+        for k in elems.keys() {
+            prop_assert!(set.contains(k));
+        }
+    }
+
+    #[test]
+    fn builder_mutates_uniquely_owned_nodes_in_place(
+        elems in hash_map(0u64..1024, "\\w{1,7}", 1usize..32),
+        extra in hash_map(0u64..2048, "\\w{1,7}", 0usize..16),
+    ) {
+        let map = elems.iter().map(|(k, v)| (*k, v.clone())).collect::<PerMap<u64, String>>();
+        let root = Arc::as_ptr(&map.data);
+
+        let mut builder = map.into_builder();
+        builder.extend(extra.iter().map(|(k, v)| (*k, v.clone())));
+        let map = builder.build();
+
+        prop_assert_eq!(root, Arc::as_ptr(&map.data));
+        for (k, v) in &extra {
+            prop_assert_eq!(Some(v), map.get(k));
+        }
+    }
+
     #[test]
     fn map_can_handle_hash_clashes(elems in hash_map(0u64..1024, "\\w{1,7}", 0usize..16)) {
         let map = PerMap::<u64, String, DegenerateBuildHasher>::with_hasher(DegenerateBuildHasher);
@@ -66,6 +301,70 @@ proptest! {
         }
     }
 
+    #[test]
+    fn total_hash_clashes_survive_removal_union_and_iteration(
+        left in hash_map(0u64..64, "\\w{1,7}", 0usize..16),
+        right in hash_map(0u64..64, "\\w{1,7}", 0usize..16),
+        removed in hash_set(0u64..64, 0usize..8),
+    ) {
+        let empty = PerMap::<u64, String, DegenerateBuildHasher>::with_hasher(DegenerateBuildHasher);
+        let left_map = left.iter().fold(empty.clone(), |m, (k, v)| m.insert(*k, v.clone()));
+        let right_map = right.iter().fold(empty.clone(), |m, (k, v)| m.insert(*k, v.clone()));
+
+        let pruned = removed.iter().fold(left_map.clone(), |m, k| m.remove(k));
+        let expected_pruned = left.iter().filter(|(k, _)| !removed.contains(*k)).collect::<HashMap<_, _>>();
+        prop_assert_eq!(expected_pruned.len(), pruned.len());
+        prop_assert_eq!(&expected_pruned, &pruned.iter().map(|e| (&e.0, &e.1)).collect::<HashMap<_, _>>());
+
+        let union = left_map.union(&right_map);
+        let expected_union = left.iter().chain(right.iter()).collect::<HashMap<_, _>>();
+        prop_assert_eq!(expected_union.len(), union.len());
+        prop_assert_eq!(expected_union.len(), union.iter().len());
+        for (k, v) in &expected_union {
+            prop_assert_eq!(Some(*v), union.get(*k));
+        }
+
+        let intersection = left_map.intersection(&right_map);
+        prop_assert_eq!(left.keys().filter(|k| right.contains_key(*k)).count(), intersection.len());
+
+        let changes = left_map.diff(&pruned).count();
+        prop_assert_eq!(left.len() - expected_pruned.len(), changes);
+
+        let rebuilt = expected_pruned.iter().fold(empty, |m, (k, v)| m.insert(**k, (*v).clone()));
+        prop_assert!(rebuilt == pruned);
+        prop_assert_eq!(expected_pruned.len(), pruned.into_iter().count());
+    }
+
+    #[test]
+    fn leaves_at_any_depth_are_combined_with_branches(
+        shallow in hash_map(0u64..1024, "\\w{1,7}", 0usize..16),
+        deep in hash_map(0u64..1024, "\\w{1,7}", 0usize..16),
+    ) {
+        let shallow_map = shallow.iter()
+            .map(|(k, v)| shallow_leaf(*k, v.clone()))
+            .fold(PerMap::empty(), |m, leaf| m.union(&leaf));
+        let deep_map = deep.iter().fold(PerMap::<u64, String>::empty(), |m, (k, v)| m.insert(*k, v.clone()));
+
+        let union = shallow_map.union(&deep_map);
+        let expected = shallow.iter().chain(deep.iter()).collect::<HashMap<_, _>>();
+        prop_assert_eq!(expected.len(), union.len());
+        prop_assert_eq!(&expected, &union.iter().map(|e| (&e.0, &e.1)).collect::<HashMap<_, _>>());
+
+        let difference = deep_map.difference(&shallow_map);
+        prop_assert_eq!(deep.keys().filter(|k| !shallow.contains_key(*k)).count(), difference.len());
+
+        let symmetric_difference = shallow_map.symmetric_difference(&deep_map);
+        prop_assert_eq!(
+            shallow.keys().chain(deep.keys()).filter(|k| shallow.contains_key(*k) != deep.contains_key(*k)).count(),
+            symmetric_difference.len()
+        );
+
+        let rebuilt = shallow.iter().fold(PerMap::<u64, String>::empty(), |m, (k, v)| m.insert(*k, v.clone()));
+        prop_assert_eq!(0, rebuilt.diff(&shallow_map).count());
+        let with_deep = deep.iter().fold(shallow_map, |m, (k, v)| m.insert(*k, v.clone()));
+        prop_assert_eq!(0, union.diff(&with_deep).count());
+    }
+
     #[test]
     fn union_of_maps_preserves_all_keys_and_has_values_from_right_side(
         left_only in hash_map(0u64..1024, "\\w{1,7}", 0usize..16),
@@ -99,6 +398,146 @@ proptest! {
         }
     }
 
+    #[test]
+    fn union_with_resolves_values_of_common_keys(
+        left in hash_map(0u64..64, 0u64..1024, 0usize..16),
+        right in hash_map(0u64..64, 0u64..1024, 0usize..16),
+    ) {
+        let left_map = left.iter().fold(PerMap::<u64, u64>::empty(), |m, (k, v)| m.insert(*k, *v));
+        let right_map = right.iter().fold(PerMap::<u64, u64>::empty(), |m, (k, v)| m.insert(*k, *v));
+
+        let res = left_map.union_with(&right_map, |_, l, r| *l.max(r));
+
+        let keys = left.keys().chain(right.keys()).collect::<HashSet<_>>();
+        prop_assert_eq!(keys.len(), res.len());
+        for k in keys {
+            let expected = left.get(k).max(right.get(k));
+            prop_assert_eq!(expected, res.get(k));
+        }
+
+        let doubled = left_map.union_with(&left_map, |_, l, r| l + r);
+        for (k, v) in &left {
+            prop_assert_eq!(Some(&(2 * v)), doubled.get(k));
+        }
+    }
+
+    #[test]
+    fn union_reuses_shared_and_one_sided_subtrees(
+        common in hash_map(0u64..1024, "\\w{1,7}", 1usize..32),
+        left_extra in hash_map(0u64..1024, "\\w{1,7}", 0usize..4),
+        right_extra in hash_map(0u64..1024, "\\w{1,7}", 0usize..4),
+    ) {
+        let parent = common.iter().fold(PerMap::<u64, String>::empty(), |m, (k, v)| m.insert(*k, v.clone()));
+        let left = left_extra.iter().fold(parent.clone(), |m, (k, v)| m.insert(*k, v.clone()));
+        let right = right_extra.iter().fold(parent.clone(), |m, (k, v)| m.insert(*k, v.clone()));
+
+        prop_assert!(Arc::ptr_eq(&left.data, &left.union(&left).data));
+        prop_assert!(Arc::ptr_eq(&left.data, &left.union(&PerMap::empty()).data));
+        prop_assert!(Arc::ptr_eq(&left.data, &PerMap::empty().union(&left).data));
+
+        let res = left.union(&right);
+        let (Node::Branch { data: left_data, .. }, Node::Branch { data: right_data, .. }, Node::Branch { data: res_data, .. }) =
+            (left.data.as_ref(), right.data.as_ref(), res.data.as_ref())
+        else {
+            unreachable!()
+        };
+        for k in res_data.keys() {
+            let expected = match (left_data.get(k), right_data.get(k)) {
+                (Some(l), Some(r)) if Arc::ptr_eq(l, r) => Some(l),
+                (Some(l), None) => Some(l),
+                (None, Some(r)) => Some(r),
+                _ => None,
+            };
+            if let Some(expected) = expected {
+                prop_assert!(Arc::ptr_eq(expected, res_data.get(k).unwrap()));
+            }
+        }
+    }
+
+    #[test]
+    fn set_operations_match_std_on_maps_sharing_a_parent(
+        common in hash_map(0u64..1024, "\\w{1,7}", 0usize..16),
+        left_extra in hash_map(0u64..1024, "\\w{1,7}", 0usize..16),
+        right_extra in hash_map(0u64..1024, "\\w{1,7}", 0usize..16),
+    ) {
+        let parent = common.iter().fold(PerMap::<u64, String>::empty(), |m, (k, v)| m.insert(*k, v.clone()));
+        let left_map = left_extra.iter().fold(parent.clone(), |m, (k, v)| m.insert(*k, v.clone()));
+        let right_map = right_extra.iter().fold(parent, |m, (k, v)| m.insert(*k, v.clone()));
+
+        let left = common.iter().chain(left_extra.iter()).collect::<HashMap<_, _>>();
+        let right = common.iter().chain(right_extra.iter()).collect::<HashMap<_, _>>();
+
+        let intersection = left_map.intersection(&right_map);
+        let difference = left_map.difference(&right_map);
+        let symmetric_difference = left_map.symmetric_difference(&right_map);
+
+        let expected_intersection = right.iter().filter(|(k, _)| left.contains_key(*k)).collect::<HashMap<_, _>>();
+        let expected_difference = left.iter().filter(|(k, _)| !right.contains_key(*k)).collect::<HashMap<_, _>>();
+        let expected_symmetric_difference = right.iter()
+            .filter(|(k, _)| !left.contains_key(*k))
+            .chain(expected_difference.clone())
+            .collect::<HashMap<_, _>>();
+
+        for (res, expected) in [
+            (intersection, expected_intersection),
+            (difference, expected_difference),
+            (symmetric_difference, expected_symmetric_difference),
+        ] {
+            prop_assert_eq!(expected.len(), res.len());
+            prop_assert_eq!(expected.len(), res.iter().count());
+            for (k, v) in expected {
+                prop_assert_eq!(Some(*v), res.get(*k));
+            }
+        }
+    }
+
+    #[test]
+    fn intersection_with_resolves_values_of_common_keys(
+        left in hash_map(0u64..64, 0u64..1024, 0usize..16),
+        right in hash_map(0u64..64, 0u64..1024, 0usize..16),
+    ) {
+        let left_map = left.iter().fold(PerMap::<u64, u64>::empty(), |m, (k, v)| m.insert(*k, *v));
+        let right_map = right.iter().fold(PerMap::<u64, u64>::empty(), |m, (k, v)| m.insert(*k, *v));
+
+        let res = left_map.intersection_with(&right_map, |_, l, r| l + r);
+
+        let expected = left.iter()
+            .filter_map(|(k, l)| right.get(k).map(|r| (*k, l + r)))
+            .collect::<HashMap<_, _>>();
+        prop_assert_eq!(expected.len(), res.len());
+        for (k, v) in expected {
+            prop_assert_eq!(Some(&v), res.get(&k));
+        }
+
+        let doubled = left_map.intersection_with(&left_map, |_, l, r| l + r);
+        for (k, v) in &left {
+            prop_assert_eq!(Some(&(2 * v)), doubled.get(k));
+        }
+    }
+
+    #[test]
+    fn set_operations_on_sets(
+        left in hash_set(0u64..128, 0usize..32),
+        right in hash_set(0u64..128, 0usize..32),
+    ) {
+        let left_set = left.iter().fold(PerSet::<u64>::empty(), |s, k| s.insert(*k));
+        let right_set = right.iter().fold(PerSet::<u64>::empty(), |s, k| s.insert(*k));
+
+        let intersection = left_set.intersection(&right_set);
+        let difference = left_set.difference(&right_set);
+        let symmetric_difference = left_set.symmetric_difference(&right_set);
+
+        prop_assert_eq!(left.intersection(&right).count(), intersection.len());
+        prop_assert_eq!(left.difference(&right).count(), difference.len());
+        prop_assert_eq!(left.symmetric_difference(&right).count(), symmetric_difference.len());
+
+        for k in 0u64..128 {
+            prop_assert_eq!(left.contains(&k) && right.contains(&k), intersection.contains(&k));
+            prop_assert_eq!(left.contains(&k) && !right.contains(&k), difference.contains(&k));
+            prop_assert_eq!(left.contains(&k) != right.contains(&k), symmetric_difference.contains(&k));
+        }
+    }
+
     #[test]
     fn iterator_returns_all_elements(elems in hash_map(0u64..1024, "\\w{1,7}", 0usize..16)) {
         let map = PerMap::<u64, String>::empty();
@@ -115,9 +554,1013 @@ proptest! {
         }
     }
 
+    #[test]
+    fn keys_values_and_sets_iterate_all_elements(elems in hash_map(0u64..1024, "\\w{1,7}", 0usize..16)) {
+        let map = elems.iter().map(|(k, v)| (*k, v.clone())).collect::<PerMap<u64, String>>();
+
+        let mut iter = map.iter();
+        prop_assert_eq!(elems.len(), iter.len());
+        iter.next();
+        prop_assert_eq!(elems.len().saturating_sub(1), iter.len());
+
+        prop_assert_eq!(elems.len(), map.keys().len());
+        prop_assert_eq!(elems.keys().collect::<HashSet<_>>(), map.keys().collect::<HashSet<_>>());
+        let mut values = map.values().collect::<Vec<_>>();
+        let mut expected_values = elems.values().collect::<Vec<_>>();
+        values.sort();
+        expected_values.sort();
+        prop_assert_eq!(expected_values, values);
+
+        let set = elems.keys().copied().collect::<PerSet<u64>>();
+        prop_assert_eq!(elems.len(), set.iter().len());
+        prop_assert_eq!(elems.keys().copied().collect::<HashSet<_>>(), set.iter().map(|k| *k).collect::<HashSet<_>>());
+        prop_assert_eq!(elems.keys().copied().collect::<HashSet<_>>(), set.into_iter().collect::<HashSet<_>>());
+    }
+
+    #[test]
+    fn owned_iterator_returns_all_elements_of_unique_and_shared_maps(
+        elems in hash_map(0u64..1024, "\\w{1,7}", 0usize..16),
+        extra in hash_map(1024u64..2048, "\\w{1,7}", 0usize..4),
+    ) {
+        let map = elems.iter().map(|(k, v)| (*k, v.clone())).collect::<PerMap<u64, String>>();
+        let shared = extra.iter().fold(map.clone(), |m, (k, v)| m.insert(*k, v.clone()));
+
+        let iter = shared.into_iter();
+        prop_assert_eq!(elems.len() + extra.len(), iter.len());
+        let expected = elems.iter().chain(extra.iter()).map(|(k, v)| (*k, v.clone())).collect::<HashMap<_, _>>();
+        prop_assert_eq!(&expected, &iter.collect::<HashMap<_, _>>());
+
+        for (k, v) in &elems {
+            prop_assert_eq!(Some(v), map.get(k));
+        }
+        prop_assert_eq!(elems, map.into_iter().collect::<HashMap<_, _>>());
+    }
+
+    #[test]
+    fn keys_are_stored_in_the_shallowest_branch_where_their_prefix_is_unique(
+        elems in hash_map(0u64..1024, "\\w{1,7}", 0usize..32),
+        removed in hash_set(0u64..1024, 0usize..16),
+    ) {
+        let expected = elems.iter().filter(|(k, _)| !removed.contains(*k)).collect::<HashMap<_, _>>();
+
+        let map = elems.iter().fold(PerMap::empty(), |m, (k, v)| m.insert(*k, v.clone()));
+        let map = removed.iter().fold(map, |m, k| m.remove(k));
+        prop_assert!(is_compact(&map.data, 0));
+        prop_assert_eq!(expected.len(), map.len());
+
+        let prefixed = elems.iter().fold(PerMap::with_hasher(SharedPrefixBuildHasher), |m, (k, v)| m.insert(*k, v.clone()));
+        let prefixed = removed.iter().fold(prefixed, |m, k| m.remove(k));
+        prop_assert!(is_compact(&prefixed.data, 0));
+        prop_assert_eq!(&expected, &prefixed.iter().map(|e| (&e.0, &e.1)).collect::<HashMap<_, _>>());
+
+        let rebuilt = expected.iter().map(|(k, v)| (**k, (*v).clone())).collect::<PerMap<_, _, SharedPrefixBuildHasher>>();
+        prop_assert!(is_compact(&rebuilt.data, 0));
+        prop_assert!(rebuilt == prefixed);
+    }
+
+    #[test]
+    fn maps_with_any_branching_factor_match_std(
+        elems in hash_map(0u64..4096, any::<u64>(), 0usize..128),
+        removed in hash_set(0u64..4096, 0usize..32),
+        other in hash_map(0u64..4096, any::<u64>(), 0usize..64),
+    ) {
+        matches_model_with_branching::<FxBuildHasher, 2>(&elems, &removed, &other)?;
+        matches_model_with_branching::<FxBuildHasher, 8>(&elems, &removed, &other)?;
+        matches_model_with_branching::<FxBuildHasher, 32>(&elems, &removed, &other)?;
+        matches_model_with_branching::<FxBuildHasher, 64>(&elems, &removed, &other)?;
+        matches_model_with_branching::<SharedPrefixBuildHasher, 32>(&elems, &removed, &other)?;
+        matches_model_with_branching::<SharedPrefixBuildHasher, 64>(&elems, &removed, &other)?;
+        matches_model_with_branching::<DegenerateBuildHasher, 8>(&elems, &removed, &other)?;
+    }
+
+    #[test]
+    fn raw_hash_lookups_agree_with_hashed_ones(
+        elems in hash_map("\\w{1,7}", any::<u64>(), 0usize..64),
+        probes in vec("\\w{1,7}", 1usize..16),
+    ) {
+        let map = elems.iter().map(|(k, v)| (k.clone(), *v)).collect::<PerMap<String, u64>>();
+        let rehashed = elems.iter().fold(PerMap::empty(), |m, (k, v)| {
+            m.insert_with_hash(FxBuildHasher.hash_one(k), k.clone(), *v)
+        });
+        prop_assert!(map == rehashed);
+        prop_assert!(is_compact(&rehashed.data, 0));
+
+        for probe in probes.iter().map(String::as_str).chain(elems.keys().map(String::as_str)) {
+            let hash = FxBuildHasher.hash_one(probe);
+            let expected = elems.get_key_value(probe).map(|(k, v)| (k.as_str(), v));
+            prop_assert_eq!(expected, map.get_key_value(probe).map(|(k, v)| (k.as_str(), v)));
+            prop_assert_eq!(expected.map(|(_, v)| v), map.get_with_hash(hash, probe));
+            prop_assert_eq!(expected.is_some(), map.contains_key(probe));
+            prop_assert_eq!(expected.is_some(), map.contains_key_with_hash(hash, probe));
+
+            let (removed, inserted) = match map.raw_entry().from_hash(hash, |k| k == probe) {
+                RawEntry::Occupied(entry) => {
+                    prop_assert_eq!(expected, Some((entry.key().as_str(), entry.get())));
+                    (entry.remove(), map.raw_entry().from_key(probe).and_modify(|_, v| v + 1))
+                }
+                RawEntry::Vacant(entry) => {
+                    prop_assert!(expected.is_none());
+                    (map.clone(), entry.insert(probe.to_owned(), 0))
+                }
+            };
+            prop_assert!(removed == map.remove(probe));
+            let value = expected.map_or(0, |(_, v)| v + 1);
+            prop_assert!(inserted == map.insert(probe.to_owned(), value));
+            prop_assert!(is_compact(&removed.data, 0));
+            prop_assert!(is_compact(&inserted.data, 0));
+        }
+
+        let colliding = elems.keys().fold(PerMap::<_, _, _, 16>::with_hasher(DegenerateBuildHasher), |m, k| {
+            m.raw_entry().from_key_hashed_nocheck(7, k.as_str()).or_insert(k.clone(), 1u64)
+        });
+        prop_assert_eq!(elems.len(), colliding.len());
+        for key in elems.keys() {
+            prop_assert_eq!(Some(&1), colliding.get_with_hash(7, key.as_str()));
+            prop_assert_eq!(None, colliding.get_with_hash(8, key.as_str()));
+        }
+    }
+
+    #[test]
+    fn filtering_and_mapping_keep_untouched_subtrees(
+        elems in hash_map(0u64..4096, any::<u64>(), 0usize..128),
+        modulus in 1u64..6,
+        dropped in 0u64..4096,
+    ) {
+        let map = elems.iter().map(|(k, v)| (*k, *v)).collect::<PerMap<u64, u64>>();
+        let keep = |k: &u64, v: &u64| !(k ^ v).is_multiple_of(modulus);
+        let expected = elems.iter().filter(|(k, v)| keep(k, v)).collect::<HashMap<_, _>>();
+
+        let filtered = map.filter(keep);
+        let mut retained = map.clone();
+        retained.retain(keep);
+        let (accepted, rejected) = map.partition(keep);
+        for candidate in [&filtered, &retained, &accepted] {
+            prop_assert_eq!(expected.len(), candidate.len());
+            prop_assert!(expected.iter().all(|(k, v)| candidate.get(*k) == Some(*v)));
+            prop_assert!(is_compact(&candidate.data, 0));
+            prop_assert!(filtered == *candidate);
+        }
+        prop_assert_eq!(map.len() - expected.len(), rejected.len());
+        prop_assert!(is_compact(&rejected.data, 0));
+        prop_assert!(rejected.iter().all(|entry| !keep(&entry.0, &entry.1)));
+        prop_assert!(accepted.union(&rejected) == map);
+
+        prop_assert!(Arc::ptr_eq(&map.data, &map.filter(|_, _| true).data));
+        let (all, none) = map.partition(|_, _| true);
+        prop_assert!(Arc::ptr_eq(&map.data, &all.data));
+        prop_assert!(none.is_empty());
+        let (Node::Branch { data: before, .. }, Node::Branch { data: after, .. }) =
+            (&*map.data, &*map.filter(|k, _| *k != dropped).data) else {
+            unreachable!();
+        };
+        let touched = before.keys().into_iter()
+            .filter(|k| !after.get(*k).is_some_and(|a| Arc::ptr_eq(a, before.get(*k).unwrap())))
+            .count();
+        prop_assert!(touched <= usize::from(elems.contains_key(&dropped)));
+
+        let mapped = map.map_values(|k, v| k.wrapping_add(*v));
+        prop_assert_eq!(map.len(), mapped.len());
+        prop_assert!(elems.iter().all(|(k, v)| mapped.get(k) == Some(&k.wrapping_add(*v))));
+        let expected = map.filter(keep).map_values(|_, v| v.to_string());
+        let filter_mapped = map.filter_map(|k, v| keep(k, v).then(|| v.to_string()));
+        prop_assert!(expected == filter_mapped);
+        prop_assert!(is_compact(&filter_mapped.data, 0));
+    }
+
+    #[test]
+    fn cursors_walk_seek_and_edit_like_the_map(
+        elems in hash_map(0u64..4096, any::<u64>(), 0usize..96),
+        probes in vec(0u64..4096, 1usize..16),
+        edits in vec((0u64..4096, proptest::option::of(any::<u64>())), 0usize..16),
+    ) {
+        cursor_matches_map::<FxBuildHasher, 16>(&elems, &probes, &edits)?;
+        cursor_matches_map::<FxBuildHasher, 2>(&elems, &probes, &edits)?;
+        cursor_matches_map::<FxBuildHasher, 64>(&elems, &probes, &edits)?;
+        cursor_matches_map::<SharedPrefixBuildHasher, 16>(&elems, &probes, &edits)?;
+        cursor_matches_map::<DegenerateBuildHasher, 16>(&elems, &probes, &edits)?;
+    }
+
+    #[test]
+    fn history_reports_exactly_what_dropping_versions_frees(
+        elems in hash_map(0u64..1024, any::<u64>(), 0usize..64),
+        changes in vec((0u64..1024, proptest::option::of(any::<u64>())), 1usize..24),
+        kept in 0usize..8,
+    ) {
+        let mut history = PerMapHistory::new();
+        let mut map = elems.iter().map(|(k, v)| (*k, *v)).collect::<PerMap<u64, u64>>();
+        prop_assert_eq!(0, history.record(map.clone()));
+        for (k, v) in &changes {
+            map = match v {
+                Some(v) => map.insert(*k, *v),
+                None => map.remove(k),
+            };
+            history.record(map.clone());
+        }
+        drop(map);
+        prop_assert_eq!(changes.len() + 1, history.len());
+
+        let total = history.stats();
+        let dropped = history.len().saturating_sub(kept);
+        let oldest = history.get(0).unwrap().clone();
+        let pinned = history.drop_older_than(1);
+        prop_assert_eq!(Released { versions: 1, nodes: 0, entries: 0 }, pinned);
+        prop_assert!(history.get(0).is_none());
+        prop_assert_eq!(1, history.iter().next().unwrap().0);
+        prop_assert_eq!(elems.len(), oldest.len());
+        drop(oldest);
+
+        let reachable = |maps: &[&PerMap<u64, u64>]| {
+            let mut nodes = HashSet::new();
+            let mut entries = HashSet::new();
+            let mut pending = maps.iter().map(|map| &map.data).collect::<Vec<_>>();
+            while let Some(node) = pending.pop() {
+                if nodes.insert(Arc::as_ptr(node)) {
+                    match &**node {
+                        Node::Leaf { data, .. } => entries.extend(data.iter().map(Arc::as_ptr)),
+                        Node::Branch { data, .. } => pending.extend(data.iter()),
+                    }
+                }
+            }
+            (nodes, entries)
+        };
+        let versions = history.iter().map(|(_, map)| map).collect::<Vec<_>>();
+        let split = versions.len().saturating_sub(kept);
+        let (all_nodes, all_entries) = reachable(&versions);
+        let (kept_nodes, kept_entries) = reachable(&versions[split..]);
+        let expected = Released {
+            versions: split,
+            nodes: all_nodes.difference(&kept_nodes).count(),
+            entries: all_entries.difference(&kept_entries).count(),
+        };
+        drop(versions);
+
+        let latest = history.latest().map(|(generation, map)| (generation, map.len()));
+        prop_assert_eq!(expected, history.keep_latest(kept));
+        prop_assert_eq!(kept.min(changes.len()), history.len());
+        prop_assert_eq!(latest.filter(|_| kept > 0), history.latest().map(|(generation, map)| (generation, map.len())));
+        prop_assert!(history.iter().all(|(generation, _)| generation as usize >= dropped));
+        prop_assert!(history.stats().nodes() <= total.nodes());
+    }
+
+    #[test]
+    fn ord_map_matches_std_and_keeps_older_snapshots(
+        elems in hash_map(0u64..1024, "\\w{1,7}", 0usize..64),
+        removed in hash_set(0u64..1024, 0usize..32),
+    ) {
+        let full = elems.iter().fold(PerOrdMap::empty(), |m, (k, v)| m.insert(*k, v.clone()));
+        let pruned = removed.iter().fold(full.clone(), |m, k| m.remove(k));
+        prop_assert!(is_balanced(&full.data).is_some());
+        prop_assert!(is_balanced(&pruned.data).is_some());
+
+        let expected = elems.iter().collect::<BTreeMap<_, _>>();
+        prop_assert_eq!(expected.len(), full.iter().len());
+        prop_assert_eq!(expected.into_iter().collect::<Vec<_>>(), full.iter().map(|e| (&e.0, &e.1)).collect::<Vec<_>>());
+
+        let expected = elems.iter().filter(|(k, _)| !removed.contains(*k)).collect::<BTreeMap<_, _>>();
+        prop_assert_eq!(&expected, &pruned.iter().map(|e| (&e.0, &e.1)).collect::<BTreeMap<_, _>>());
+        for k in &removed {
+            prop_assert_eq!(None, pruned.get(k));
+            prop_assert_eq!(elems.get(k), full.get(k));
+        }
+        prop_assert_eq!(expected.first_key_value().map(|(k, v)| (**k, *v)), pruned.first().map(|e| (e.0, &e.1)));
+        prop_assert_eq!(expected.last_key_value().map(|(k, v)| (**k, *v)), pruned.last().map(|e| (e.0, &e.1)));
+
+        let rebuilt = expected.iter().map(|(k, v)| (**k, (*v).clone())).collect::<PerOrdMap<_, _>>();
+        prop_assert_eq!(&rebuilt, &pruned);
+
+        let by_name = elems.iter().map(|(k, v)| (v.clone(), *k)).collect::<PerOrdMap<String, u64>>();
+        let names = elems.values().cloned().collect::<PerOrdSet<String>>();
+        for name in elems.values().map(String::as_str) {
+            prop_assert!(by_name.get(name).is_some() && names.contains(name));
+            prop_assert_eq!(by_name.len() - 1, by_name.remove(name).len());
+            prop_assert_eq!(names.range::<str, _>((Bound::Unbounded, Bound::Excluded(name))).len(), names.split_off(name).0.len());
+        }
+    }
+
+    #[test]
+    fn ord_map_ranges_and_splits_match_std(
+        elems in hash_map(0u64..256, "\\w{1,7}", 0usize..64),
+        start in prop_oneof![Just(Bound::Unbounded), (0u64..256).prop_map(Bound::Included), (0u64..256).prop_map(Bound::Excluded)],
+        end in prop_oneof![Just(Bound::Unbounded), (0u64..256).prop_map(Bound::Included), (0u64..256).prop_map(Bound::Excluded)],
+        pivot in 0u64..256,
+    ) {
+        let expected = elems.iter().map(|(k, v)| (*k, v.clone())).collect::<BTreeMap<_, _>>();
+        let map = elems.iter().map(|(k, v)| (*k, v.clone())).collect::<PerOrdMap<_, _>>();
+
+        let in_range = |k: &u64| (start, end).contains(k);
+        let range = map.range((start, end));
+        prop_assert_eq!(expected.keys().filter(|k| in_range(k)).count(), range.len());
+        prop_assert_eq!(
+            expected.keys().filter(|k| in_range(k)).collect::<Vec<_>>(),
+            range.map(|e| &e.0).collect::<Vec<_>>()
+        );
+
+        let (left, right) = map.split_off(&pivot);
+        prop_assert!(is_balanced(&left.data).is_some());
+        prop_assert!(is_balanced(&right.data).is_some());
+        let mut expected_left = expected.clone();
+        let expected_right = expected_left.split_off(&pivot);
+        prop_assert_eq!(expected_left.into_iter().collect::<Vec<_>>(), left.iter().map(|e| (**e).clone()).collect::<Vec<_>>());
+        prop_assert_eq!(expected_right.into_iter().collect::<Vec<_>>(), right.iter().map(|e| (**e).clone()).collect::<Vec<_>>());
+        prop_assert_eq!(elems.len(), map.len());
+    }
+
+    #[test]
+    fn ord_sets_are_ordered(
+        elems in hash_set(0u64..1024, 0usize..64),
+        removed in hash_set(0u64..1024, 0usize..32),
+        pivot in 0u64..1024,
+    ) {
+        let set = elems.iter().copied().collect::<PerOrdSet<_>>();
+        let pruned = removed.iter().fold(set.clone(), |s, k| s.remove(k));
+        let expected = elems.difference(&removed).copied().collect::<BTreeSet<_>>();
+
+        prop_assert_eq!(expected.iter().copied().collect::<Vec<_>>(), pruned.iter().map(|k| *k).collect::<Vec<_>>());
+        prop_assert_eq!(expected.first(), pruned.first());
+        prop_assert_eq!(expected.last(), pruned.last());
+        for k in &elems {
+            prop_assert!(set.contains(k));
+            prop_assert_eq!(!removed.contains(k), pruned.contains(k));
+        }
+
+        let (left, right) = pruned.split_off(&pivot);
+        prop_assert_eq!(expected.range(..pivot).count(), left.len());
+        prop_assert_eq!(expected.range(pivot..).copied().collect::<Vec<_>>(), right.iter().map(|k| *k).collect::<Vec<_>>());
+        prop_assert_eq!(expected.range(pivot..).count(), pruned.range(pivot..).len());
+    }
+
+    #[test]
+    fn per_vec_matches_std_vec_and_keeps_older_snapshots(
+        elems in vec("\\w{1,7}", 0usize..300),
+        pops in 0usize..40,
+        updates in vec((0usize..300, "\\w{1,7}"), 0usize..8),
+    ) {
+        let full = elems.iter().fold(PerVec::empty(), |v, e| v.push_back(e.clone()));
+        prop_assert_eq!(elems.len(), full.len());
+        prop_assert_eq!(elems.len(), full.iter().len());
+        prop_assert_eq!(&elems, &full.iter().cloned().collect::<Vec<_>>());
+
+        let popped = (0..pops).fold(full.clone(), |v, _| v.pop_back());
+        let mut expected = elems.clone();
+        expected.truncate(elems.len().saturating_sub(pops));
+        prop_assert_eq!(&expected, &popped.iter().cloned().collect::<Vec<_>>());
+        prop_assert_eq!(expected.last(), popped.last());
+
+        let mut updated = popped.clone();
+        for (index, value) in &updates {
+            match updated.set(*index, value.clone()) {
+                Some(next) => {
+                    expected[*index] = value.clone();
+                    updated = next;
+                }
+                None => prop_assert!(*index >= expected.len()),
+            }
+        }
+        for (i, value) in expected.iter().enumerate() {
+            prop_assert_eq!(Some(value), updated.get(i));
+        }
+        prop_assert_eq!(None, updated.get(expected.len()));
+        prop_assert_eq!(&elems, &full.iter().cloned().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn per_vec_slices_and_concatenations_match_std_vec(
+        left in vec(0u32..1000, 0usize..300),
+        right in vec(0u32..1000, 0usize..300),
+        start in 0usize..320,
+        len in 0usize..320,
+    ) {
+        let left_vec = left.iter().copied().collect::<PerVec<_>>();
+        let right_vec = right.iter().copied().collect::<PerVec<_>>();
+
+        let end = (start + len).min(left.len());
+        let start = start.min(end);
+        let slice = left_vec.slice(start..start + len);
+        prop_assert_eq!(end - start, slice.len());
+        prop_assert_eq!(&left[start..end], &slice.iter().copied().collect::<Vec<_>>()[..]);
+        let grown = slice.push_back(7).slice(1..);
+        let expected = left[start..end].iter().copied().chain([7]).skip(1).collect::<Vec<_>>();
+        prop_assert_eq!(&expected, &grown.iter().copied().collect::<Vec<_>>());
+
+        let concatenated = slice.concat(&right_vec);
+        let expected = left[start..end].iter().chain(&right).copied().collect::<Vec<_>>();
+        prop_assert_eq!(&expected, &concatenated.iter().copied().collect::<Vec<_>>());
+        prop_assert!(concatenated == expected.iter().copied().collect::<PerVec<_>>());
+
+        let aligned = left_vec.slice(..left.len() / 16 * 16);
+        let grafted = aligned.concat(&right_vec);
+        let expected = left[..aligned.len()].iter().chain(&right).copied().collect::<Vec<_>>();
+        prop_assert_eq!(&expected, &grafted.iter().copied().collect::<Vec<_>>());
+        prop_assert_eq!(expected.len(), grafted.len());
+        prop_assert_eq!(expected.last(), grafted.last());
+        let mut shared = Vec::new();
+        vec_leaves(&grafted.data, &mut shared);
+        let mut reused = Vec::new();
+        vec_leaves(&right_vec.data, &mut reused);
+        prop_assert!(right.is_empty() || reused.iter().all(|leaf| shared.contains(leaf)));
+        prop_assert_eq!(&left, &left_vec.iter().copied().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn multimap_tracks_sets_of_values_per_key(
+        pairs in vec((0u64..64, 0u32..16), 0usize..64),
+        removed in vec((0u64..64, 0u32..16), 0usize..32),
+        cleared in hash_set(0u64..64, 0usize..8),
+    ) {
+        let full = pairs.iter().copied().collect::<PerMultiMap<u64, u32>>();
+        let pruned = removed.iter().fold(full.clone(), |m, (k, v)| m.remove(k, v));
+        let pruned = cleared.iter().fold(pruned, |m, k| m.remove_all(k));
+
+        let mut expected = HashMap::<u64, HashSet<u32>>::new();
+        for (k, v) in &pairs {
+            expected.entry(*k).or_default().insert(*v);
+        }
+        prop_assert_eq!(expected.values().map(HashSet::len).sum::<usize>(), full.len());
+        for (k, v) in &removed {
+            if let Some(values) = expected.get_mut(k) {
+                values.remove(v);
+            }
+        }
+        expected.retain(|k, values| !values.is_empty() && !cleared.contains(k));
+
+        prop_assert_eq!(expected.values().map(HashSet::len).sum::<usize>(), pruned.len());
+        prop_assert_eq!(expected.len(), pruned.keys().count());
+        let mut actual = HashMap::<u64, HashSet<u32>>::new();
+        for (k, v) in &pruned {
+            actual.entry(*k).or_default().insert(*v);
+        }
+        prop_assert_eq!(&expected, &actual);
+        for (k, v) in &pairs {
+            prop_assert!(full.contains(k, v));
+            prop_assert_eq!(expected.get(k).is_some_and(|values| values.contains(v)), pruned.contains(k, v));
+        }
+        prop_assert!(pruned.union(&full) == full);
+
+        let named = pairs.iter().map(|(k, v)| (k.to_string(), *v)).collect::<PerMultiMap<String, u32>>();
+        for (k, v) in &pairs {
+            let name = k.to_string();
+            let values = named.get(name.as_str()).map_or(0, PerSet::len);
+            prop_assert!(named.contains(name.as_str(), v));
+            prop_assert_eq!(full.get(k).map_or(0, PerSet::len), values);
+            prop_assert_eq!(named.len() - values, named.remove_all(name.as_str()).len());
+        }
+    }
+
+    #[test]
+    fn multimap_union_counts_overlapping_values_once(
+        shared in vec((0u64..64, 0u32..16), 0usize..64),
+        left in vec((0u64..64, 0u32..16), 0usize..32),
+        right in vec((0u64..64, 0u32..16), 0usize..32),
+    ) {
+        let base = shared.iter().copied().collect::<PerMultiMap<u64, u32>>();
+        let left_map = left.iter().fold(base.clone(), |m, (k, v)| m.insert(*k, *v));
+        let right_map = right.iter().fold(base, |m, (k, v)| m.insert(*k, *v));
+        let union = left_map.union(&right_map);
+
+        let expected = shared.iter().chain(&left).chain(&right).collect::<HashSet<_>>();
+        prop_assert_eq!(expected.len(), union.len());
+        prop_assert_eq!(expected.len(), union.iter().count());
+        prop_assert_eq!(union.len(), right_map.union(&left_map).len());
+        prop_assert_eq!(left_map.len(), left_map.union(&left_map).len());
+    }
+
+    #[test]
+    fn bag_counts_occurrences(
+        keys in vec(0u64..32, 0usize..128),
+        removed in vec((0u64..32, 0usize..4), 0usize..32),
+        other in vec(0u64..32, 0usize..64),
+    ) {
+        let bag = keys.iter().copied().collect::<PerBag<u64>>();
+        let pruned = removed.iter().fold(bag.clone(), |b, (k, n)| b.remove_many(k, *n));
+        let other_bag = other.iter().copied().collect::<PerBag<u64>>();
+
+        let count = |keys: &[u64]| {
+            let mut counts = HashMap::<u64, usize>::new();
+            for k in keys {
+                *counts.entry(*k).or_default() += 1;
+            }
+            counts
+        };
+        let mut expected = count(&keys);
+        for (k, n) in &removed {
+            if let Some(c) = expected.get_mut(k) {
+                *c = c.saturating_sub(*n);
+            }
+        }
+        expected.retain(|_, c| *c > 0);
+        let other_counts = count(&other);
+
+        prop_assert_eq!(keys.len(), bag.len());
+        prop_assert_eq!(expected.values().sum::<usize>(), pruned.len());
+        prop_assert_eq!(expected.len(), pruned.distinct_len());
+        prop_assert_eq!(&expected, &pruned.iter().map(|(k, c)| (*k, c)).collect::<HashMap<_, _>>());
+
+        let sum = pruned.sum(&other_bag);
+        let union = pruned.union(&other_bag);
+        let intersection = pruned.intersection(&other_bag);
+        for k in 0u64..32 {
+            let (l, r) = (expected.get(&k).copied().unwrap_or(0), other_counts.get(&k).copied().unwrap_or(0));
+            prop_assert_eq!(l + r, sum.count(&k));
+            prop_assert_eq!(l.max(r), union.count(&k));
+            prop_assert_eq!(l.min(r), intersection.count(&k));
+            prop_assert_eq!(l.min(r) > 0, intersection.contains(&k));
+        }
+        prop_assert_eq!(sum.len(), sum.iter().map(|(_, c)| c).sum::<usize>());
+        prop_assert_eq!(intersection.len(), intersection.iter().map(|(_, c)| c).sum::<usize>());
+
+        let named = keys.iter().map(u64::to_string).collect::<PerBag<String>>();
+        for (k, c) in count(&keys) {
+            let name = k.to_string();
+            prop_assert!(named.contains(name.as_str()));
+            prop_assert_eq!(c, named.count(name.as_str()));
+            prop_assert_eq!(named.len() - c, named.remove_many(name.as_str(), c).len());
+        }
+    }
+
+    #[test]
+    fn bimap_keeps_both_directions_one_to_one(
+        pairs in vec((0u64..64, 0u32..64), 0usize..64),
+        removed_left in hash_set(0u64..64, 0usize..16),
+        removed_right in hash_set(0u32..64, 0usize..16),
+    ) {
+        let mut map = PerBiMap::<u64, u32>::empty();
+        let mut expected = HashMap::<u64, u32>::new();
+        for (l, r) in &pairs {
+            match map.insert(*l, *r) {
+                Ok(next) => {
+                    prop_assert!(expected.get(l).is_none_or(|current| current == r));
+                    prop_assert!(expected.values().all(|v| v != r) || expected.get(l) == Some(r));
+                    expected.insert(*l, *r);
+                    map = next;
+                }
+                Err(BiMapError::LeftTaken { left, right }) => {
+                    prop_assert_eq!((*l, *r), (left, right));
+                    prop_assert!(expected.get(l).is_some_and(|current| current != r));
+                }
+                Err(error @ BiMapError::RightTaken { .. }) => {
+                    prop_assert!(!expected.contains_key(l));
+                    prop_assert!(expected.values().any(|v| v == r));
+                    prop_assert_eq!((*l, *r), error.into_pair());
+                }
+            }
+        }
+        let full = map.clone();
+        let full_expected = expected.clone();
+        let map = removed_left.iter().fold(map, |m, l| m.remove_left(l));
+        let map = removed_right.iter().fold(map, |m, r| m.remove_right(r));
+        expected.retain(|l, r| !removed_left.contains(l) && !removed_right.contains(r));
+
+        prop_assert_eq!(expected.len(), map.len());
+        prop_assert_eq!(&expected, &map.iter().map(|(l, r)| (*l, *r)).collect::<HashMap<_, _>>());
+        for (l, r) in &expected {
+            prop_assert_eq!(Some(r), map.get_by_left(l));
+            prop_assert_eq!(Some(l), map.get_by_right(r));
+        }
+        for r in &removed_right {
+            prop_assert_eq!(None, map.get_by_right(r));
+        }
+        for l in &removed_left {
+            prop_assert_eq!(full_expected.get(l), full.get_by_left(l));
+        }
+
+        let named = expected
+            .iter()
+            .try_fold(PerBiMap::empty(), |m, (l, r)| m.insert(l.to_string(), r.to_string()))
+            .unwrap();
+        for (l, r) in &expected {
+            let (l, r) = (l.to_string(), r.to_string());
+            prop_assert_eq!(Some(&r), named.get_by_left(l.as_str()));
+            prop_assert_eq!(Some(&l), named.get_by_right(r.as_str()));
+            prop_assert_eq!(named.len() - 1, named.remove_left(l.as_str()).len());
+            prop_assert_eq!(named.len() - 1, named.remove_right(r.as_str()).len());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn maps_and_sets_survive_a_serde_round_trip(
+        elems in hash_map(0u64..1024, "\\w{1,7}", 0usize..64),
+    ) {
+        let map = elems.iter().map(|(k, v)| (*k, v.clone())).collect::<PerMap<u64, String>>();
+        let json = serde_json::to_string(&map).unwrap();
+        prop_assert_eq!(&elems, &serde_json::from_str::<HashMap<u64, String>>(&json).unwrap());
+        prop_assert!(map == serde_json::from_str::<PerMap<u64, String>>(&json).unwrap());
+
+        let set = elems.keys().copied().collect::<PerSet<u64>>();
+        let json = serde_json::to_string(&set).unwrap();
+        prop_assert_eq!(elems.keys().copied().collect::<HashSet<_>>(), serde_json::from_str::<HashSet<u64>>(&json).unwrap());
+        prop_assert!(set == serde_json::from_str::<PerSet<u64>>(&json).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn parallel_operations_agree_with_sequential_ones(
+        left in vec((0u64..4096, any::<u64>()), 0usize..512),
+        right in hash_map(0u64..4096, any::<u64>(), 0usize..512),
+    ) {
+        use rayon::prelude::*;
+
+        let sequential = left.iter().copied().collect::<PerMap<u64, u64>>();
+        let parallel = left.par_iter().copied().collect::<PerMap<u64, u64>>();
+        prop_assert!(sequential == parallel);
+
+        let mut entries = parallel.par_iter().map(|entry| (entry.0, entry.1)).collect::<Vec<_>>();
+        entries.sort_unstable();
+        prop_assert_eq!(sequential.iter().map(|entry| (entry.0, entry.1)).collect::<BTreeMap<_, _>>().into_iter().collect::<Vec<_>>(), entries);
+
+        let right = right.into_iter().collect::<PerMap<u64, u64>>();
+        prop_assert!(sequential.union(&right) == sequential.par_union(&right));
+        prop_assert!(
+            sequential.union_with(&right, |_, l, r| l.wrapping_add(*r))
+                == sequential.par_union_with(&right, |_, l, r| l.wrapping_add(*r))
+        );
+        let same = sequential.par_union(&sequential);
+        prop_assert!(Arc::ptr_eq(&same.data, &sequential.data));
+    }
+
+    #[test]
+    fn stats_count_shared_structure_once(
+        elems in hash_map(0u64..4096, any::<u64>(), 0usize..256),
+        key in 4096u64..8192,
+    ) {
+        let map = elems.into_iter().collect::<PerMap<u64, u64>>();
+        let stats = map.stats();
+        prop_assert_eq!(map.len(), stats.entries);
+        prop_assert_eq!(stats.leaves, stats.depths.iter().sum::<usize>());
+        prop_assert_eq!(stats.leaves, stats.leaf_sizes.iter().sum::<usize>());
+        prop_assert_eq!(map.len(), stats.leaf_sizes.iter().enumerate().map(|(n, l)| n * l).sum::<usize>());
+        prop_assert_eq!(stats.branches, stats.fill.iter().sum::<usize>());
+        prop_assert_eq!(0, stats.depths.first().copied().unwrap_or(0));
+        prop_assert!((0.0..=1.0).contains(&stats.fill_ratio()));
+        prop_assert_eq!((0, 0), (stats.shared_nodes, stats.shared_bytes));
+
+        let mut collector = StatsCollector::new();
+        collector.add(&map);
+        collector.add(&map.clone());
+        let twice = collector.finish();
+        prop_assert_eq!(stats.bytes, twice.bytes);
+        prop_assert_eq!(stats.nodes(), twice.shared_nodes);
+        prop_assert_eq!(0, twice.unique_bytes());
+
+        let next = map.insert(key, key);
+        let mut collector = StatsCollector::new();
+        collector.add(&map);
+        collector.add(&next);
+        let both = collector.finish();
+        prop_assert!(map.is_empty() || both.shared_bytes > 0);
+        prop_assert_eq!(stats.bytes + next.stats().bytes, both.bytes + both.shared_bytes);
+        prop_assert_eq!(map.len() + 1, both.entries);
+
+        let set = map.keys().copied().collect::<PerSet<u64>>();
+        prop_assert_eq!(set.len(), set.stats().entries);
+    }
+
+    #[test]
+    fn dot_export_merges_shared_nodes(
+        elems in hash_map(0u64..4096, "[a-z\"\\\\]{0,4}", 0usize..128),
+        key in 4096u64..8192,
+    ) {
+        let map = elems.into_iter().collect::<PerMap<u64, String>>();
+        let next = map.insert(key, "\"new\"".to_owned());
+        let graph = dot::render([&map, &next]);
+        let mut collector = StatsCollector::new();
+        collector.add(&map);
+        collector.add(&next);
+        let stats = collector.finish();
+
+        let vertices = graph.lines().filter(|line| line.contains("[shape=box") || line.contains("[shape=circle")).count();
+        let edges = graph.lines().filter(|line| line.starts_with("  n") && line.contains("->")).count();
+        let filled = graph.lines().filter(|line| line.contains("style=filled")).count();
+        prop_assert_eq!(stats.nodes(), vertices);
+        prop_assert_eq!(stats.fill.iter().enumerate().map(|(n, b)| n * b).sum::<usize>(), edges);
+        prop_assert_eq!(stats.shared_nodes, filled);
+        let label = format!(r#"\n{key}: \"\\\"new\\\"\""#);
+        prop_assert!(graph.contains(&label));
+    }
+
+    #[test]
+    fn snapshots_round_trip_and_keep_sharing(
+        elems in hash_map(0u64..1024, "\\w{1,7}", 1usize..64),
+        changes in vec((0u64..1024, "\\w{1,7}"), 1usize..8),
+    ) {
+        let first = elems.iter().map(|(k, v)| (*k, v.clone())).collect::<PerMap<u64, String>>();
+        let history = changes.iter().fold(vec![first], |mut history, (k, v)| {
+            let next = history.last().unwrap().insert(*k, v.clone());
+            history.push(next);
+            history
+        });
+
+        let bytes = snapshot::encode(&history);
+        let decoded = snapshot::decode::<u64, String, FxBuildHasher, 16>(&bytes).unwrap();
+        prop_assert_eq!(history.len(), decoded.len());
+        for (original, decoded) in history.iter().zip(&decoded) {
+            prop_assert!(original == decoded);
+        }
+
+        let separately = history.iter().map(|map| snapshot::encode([map]).len()).sum::<usize>();
+        prop_assert!(bytes.len() < separately);
+        for (previous, next) in decoded.iter().zip(&decoded[1..]) {
+            let (Node::Branch { data: previous, .. }, Node::Branch { data: next, .. }) = (&*previous.data, &*next.data) else {
+                unreachable!();
+            };
+            let changed = next.keys().into_iter()
+                .filter(|k| !previous.get(*k).is_some_and(|p| Arc::ptr_eq(p, next.get(*k).unwrap())))
+                .count();
+            prop_assert!(changed <= 1);
+        }
+
+        for len in (0..bytes.len()).step_by(bytes.len() / 64 + 1) {
+            prop_assert!(snapshot::decode::<u64, String, FxBuildHasher, 16>(&bytes[..len]).is_err());
+        }
+        prop_assert_eq!(
+            Err(DecodeError::HashMismatch),
+            snapshot::decode::<u64, String, DegenerateBuildHasher, 16>(&bytes).map(|maps| maps.len())
+        );
+
+        let (key, value) = elems.iter().next().unwrap();
+        let mut nested = b"PMS2\x10\x13\x00".to_vec();
+        nested.extend_from_slice(&FxBuildHasher.hash_one(key).to_le_bytes());
+        nested.push(1);
+        nested.extend_from_slice(&key.to_le_bytes());
+        nested.push(0);
+        for id in 0..18u8 {
+            nested.extend_from_slice(&[1, 0xff, 0xff]);
+            nested.extend_from_slice(&[id; 16]);
+        }
+        nested.extend_from_slice(&[1, 18]);
+        prop_assert_eq!(
+            Err(DecodeError::InvalidStructure),
+            snapshot::decode::<u64, String, FxBuildHasher, 16>(&nested).map(|maps| maps.len())
+        );
+
+        let random = RandomState::new();
+        let seeded = elems.iter().fold(PerMap::with_hasher(random.clone()), |m, (k, v)| m.insert(*k, v.clone()));
+        let decoded = snapshot::decode_with_hasher::<u64, String, _, 16>(&snapshot::encode([&seeded]), random).unwrap();
+        prop_assert!(decoded.len() == 1 && decoded[0] == seeded);
+
+        let hash = FxBuildHasher.hash_one(key);
+        let leaf = Node::Leaf {
+            hash,
+            data: smallvec![Arc::new((*key, value.clone())), Arc::new((*key, value.clone()))],
+            weight: 2,
+        };
+        let mut data = SparseVec::new();
+        data.insert((hash & 0b1111) as usize, Arc::new(leaf));
+        let duplicated: PerMap<u64, String> = PerMap {
+            data: Arc::new(Node::Branch { data, weight: 2 }),
+            hasher: FxBuildHasher,
+        };
+        prop_assert_eq!(
+            Err(DecodeError::InvalidStructure),
+            snapshot::decode::<u64, String, FxBuildHasher, 16>(&snapshot::encode([&duplicated])).map(|maps| maps.len())
+        );
+    }
+}
+
+fn shallow_leaf(key: u64, value: String) -> PerMap<u64, String> {
+    let hash = FxBuildHasher.hash_one(key);
+    let leaf = Node::Leaf {
+        hash,
+        data: smallvec![Arc::new((key, value))],
+        weight: 1,
+    };
+    let mut data = SparseVec::new();
+    data.insert((hash & 0b1111) as usize, Arc::new(leaf));
+    PerMap {
+        data: Arc::new(Node::Branch { data, weight: 1 }),
+        hasher: FxBuildHasher,
+    }
+}
+
+fn is_balanced<K, V>(tree: &Tree<K, V>) -> Option<u8> {
+    let Some(node) = tree else {
+        return Some(0);
+    };
+    let left = is_balanced(&node.left)?;
+    let right = is_balanced(&node.right)?;
+    let consistent = left.abs_diff(right) <= 1
+        && ord_nodes::Node::height(tree) == left.max(right) + 1
+        && ord_nodes::Node::size(tree)
+            == ord_nodes::Node::size(&node.left) + ord_nodes::Node::size(&node.right) + 1;
+    consistent.then_some(left.max(right) + 1)
+}
+
+fn vec_leaves<T>(
+    node: &Arc<vector_nodes::Node<T>>,
+    leaves: &mut Vec<*const vector_nodes::Node<T>>,
+) {
+    match &**node {
+        vector_nodes::Node::Leaf(_) => leaves.push(Arc::as_ptr(node)),
+        vector_nodes::Node::Branch(data) => {
+            for child in data {
+                vec_leaves(child, leaves);
+            }
+        }
+    }
+}
+
+fn is_compact<K, V, const B: usize>(node: &Node<K, V, B>, shift: usize) -> bool {
+    match node {
+        Node::Leaf { .. } => true,
+        Node::Branch { data, .. } => {
+            let collapsible =
+                data.len() == 1 && data.iter().all(|n| matches!(**n, Node::Leaf { .. }));
+            (shift == 0 || !collapsible)
+                && data
+                    .iter()
+                    .all(|n| is_compact(n, shift + Node::<K, V, B>::BITS))
+        }
+    }
+}
+
+fn cursor_matches_map<S, const B: usize>(
+    elems: &HashMap<u64, u64>,
+    probes: &[u64],
+    edits: &[(u64, Option<u64>)],
+) -> Result<(), TestCaseError>
+where
+    S: BuildHasher + Clone + Default,
+{
+    let map = elems.iter().fold(
+        PerMap::<_, _, S, B>::with_hasher(S::default()),
+        |m, (k, v)| m.insert(*k, *v),
+    );
+    let walked = map.cursor().collect::<Vec<_>>();
+    prop_assert_eq!(map.len(), walked.len());
+    prop_assert!(map.iter().zip(&walked).all(|(a, b)| Arc::ptr_eq(a, b)));
+
+    let mut cursor = map.cursor();
+    for probe in probes {
+        let found = cursor.seek(probe);
+        prop_assert_eq!(elems.contains_key(probe), found);
+        let order = map.insert(*probe, 0).keys().copied().collect::<Vec<_>>();
+        let start = order.iter().position(|k| k == probe).unwrap() + usize::from(!found);
+        prop_assert_eq!(order.get(start), cursor.current().map(|(k, _)| k));
+        if found {
+            let mut node = &*map.data;
+            for index in cursor.path() {
+                let Node::Branch { data, .. } = node else {
+                    unreachable!()
+                };
+                node = data.get(index).unwrap();
+            }
+            let Node::Leaf { data, .. } = node else {
+                unreachable!()
+            };
+            prop_assert!(data.iter().any(|entry| entry.0 == *probe));
+        }
+        let following = cursor
+            .by_ref()
+            .take(3)
+            .map(|entry| entry.0)
+            .collect::<Vec<_>>();
+        prop_assert_eq!(&order[start..(start + 3).min(order.len())], &following[..]);
+    }
+
+    let mut cursor = map.cursor();
+    let mut expected = map.clone();
+    for (key, value) in edits {
+        if let Some(value) = value {
+            cursor.insert(*key, *value);
+            prop_assert_eq!(Some((key, value)), cursor.current());
+            expected = expected.insert(*key, *value);
+        } else {
+            let removed = cursor.remove(key).map(|entry| entry.0);
+            prop_assert_eq!(expected.get(key).map(|_| *key), removed);
+            expected = expected.remove(key);
+        }
+    }
+    let edited = cursor.commit();
+    prop_assert!(edited == expected);
+    prop_assert!(is_compact(&edited.data, 0));
+    prop_assert_eq!(elems.len(), map.len());
+    prop_assert!(elems.iter().all(|(k, v)| map.get(k) == Some(v)));
+    let (Node::Branch { data: before, .. }, Node::Branch { data: after, .. }) =
+        (&*map.data, &*edited.data)
+    else {
+        unreachable!();
+    };
+    let touched = after
+        .keys()
+        .into_iter()
+        .filter(|k| {
+            !before
+                .get(*k)
+                .is_some_and(|b| Arc::ptr_eq(b, after.get(*k).unwrap()))
+        })
+        .count();
+    prop_assert!(touched <= edits.len());
+
+    let mut cursor = map.cursor();
+    while let Some((_, value)) = cursor.current() {
+        if value % 2 == 0 {
+            cursor.remove_current();
+        } else {
+            let halved = value / 2;
+            cursor.set_value(halved);
+            cursor.next();
+        }
+    }
+    let expected = map.filter(|_, v| v % 2 == 1).map_values(|_, v| v / 2);
+    let edited = cursor.commit();
+    prop_assert!(edited == expected);
+    prop_assert!(is_compact(&edited.data, 0));
+    Ok(())
+}
+
+fn matches_model_with_branching<S, const B: usize>(
+    elems: &HashMap<u64, u64>,
+    removed: &HashSet<u64>,
+    other: &HashMap<u64, u64>,
+) -> Result<(), TestCaseError>
+where
+    S: BuildHasher + Clone + Default,
+{
+    let mut expected = elems.clone();
+    expected.retain(|k, _| !removed.contains(k));
+    let map = elems.iter().fold(
+        PerMap::<_, _, S, B>::with_hasher(S::default()),
+        |m, (k, v)| m.insert(*k, *v),
+    );
+    let map = removed.iter().fold(map, |m, k| m.remove(k));
+    prop_assert!(is_compact(&map.data, 0));
+    prop_assert_eq!(expected.len(), map.len());
+    let stats = map.stats();
+    prop_assert_eq!(stats.branches * B, stats.slots);
+    prop_assert_eq!(stats.branches, stats.fill.iter().sum::<usize>());
+    prop_assert_eq!(
+        &expected,
+        &map.iter().map(|e| (e.0, e.1)).collect::<HashMap<_, _>>()
+    );
+    for (k, v) in elems {
+        prop_assert_eq!(expected.get(k), map.get(k));
+        prop_assert_eq!(Some(*v), map.insert(*k, *v).get(k).copied());
+    }
+
+    let other = other
+        .iter()
+        .map(|(k, v)| (*k, *v))
+        .collect::<PerMap<_, _, S, B>>();
+    let mut union = expected.clone();
+    union.extend(other.iter().map(|e| (e.0, e.1)));
+    let merged = map.union(&other);
+    prop_assert!(is_compact(&merged.data, 0));
+    prop_assert_eq!(
+        &union,
+        &merged.iter().map(|e| (e.0, e.1)).collect::<HashMap<_, _>>()
+    );
+    let common = map.intersection(&other);
+    prop_assert!(is_compact(&common.data, 0));
+    prop_assert!(common
+        .iter()
+        .all(|e| expected.contains_key(&e.0) && other.get(&e.0).is_some()));
+    prop_assert_eq!(
+        expected.keys().filter(|k| other.get(k).is_some()).count(),
+        common.len()
+    );
+    prop_assert!(
+        map.difference(&other).union(&common)
+            == map.union(&other).difference(&other).union(&common)
+    );
+
+    let bytes = snapshot::encode([&map, &merged]);
+    let decoded = snapshot::decode::<u64, u64, S, B>(&bytes).unwrap();
+    prop_assert!(decoded[0] == map && decoded[1] == merged);
+    if B != 16 {
+        prop_assert_eq!(
+            Err(DecodeError::InvalidBranching(B)),
+            snapshot::decode::<u64, u64, S, 16>(&bytes).map(|maps| maps.len())
+        );
+    }
+    Ok(())
+}
+
+#[derive(Clone, Default)]
+struct SharedPrefixBuildHasher;
+
+struct SharedPrefixHasher(u64);
+
+impl BuildHasher for SharedPrefixBuildHasher {
+    type Hasher = SharedPrefixHasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        SharedPrefixHasher(0)
+    }
+}
+
+impl Hasher for SharedPrefixHasher {
+    fn finish(&self) -> u64 {
+        self.0 << 40
+    }
+
+    fn write(&mut self, _: &[u8]) {}
+
+    fn write_u64(&mut self, i: u64) {
+        self.0 = i;
+    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 struct DegenerateBuildHasher;
 
 struct DegenerateHasher;
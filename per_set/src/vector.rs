@@ -0,0 +1,333 @@
+use std::{
+    hash::{Hash, Hasher},
+    ops::{Bound, RangeBounds},
+    sync::Arc,
+};
+
+use crate::vector_nodes::Node;
+
+#[derive(Debug)]
+pub struct PerVec<T> {
+    pub(crate) data: Arc<Node<T>>,
+    shift: usize,
+    offset: usize,
+    len: usize,
+}
+
+impl<T> PerVec<T> {
+    #[must_use]
+    pub fn empty() -> Self {
+        PerVec {
+            data: Arc::new(Node::empty(0)),
+            shift: 0,
+            offset: 0,
+            len: 0,
+        }
+    }
+
+    /// Drops root branches whose only populated child holds every element,
+    /// so that slicing away the front does not leave an ever-deeper tree.
+    fn compacted(data: Option<Arc<Node<T>>>, shift: usize, offset: usize, len: usize) -> Self {
+        let Some(mut data) = data else {
+            return PerVec::empty();
+        };
+        let (mut shift, mut offset) = (shift, offset);
+        while let Node::Branch(children) = &*data {
+            let first = Node::<T>::index(offset, shift);
+            if first != Node::<T>::index(offset + len - 1, shift) {
+                break;
+            }
+            let child = Arc::clone(children.get(first).unwrap());
+            offset -= first << shift;
+            shift -= 4;
+            data = child;
+        }
+        PerVec {
+            data,
+            shift,
+            offset,
+            len,
+        }
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index < self.len {
+            self.data
+                .get(self.shift, self.offset + index)
+                .map(|value| &**value)
+        } else {
+            None
+        }
+    }
+
+    #[must_use]
+    pub fn first(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    #[must_use]
+    pub fn last(&self) -> Option<&T> {
+        self.get(self.len.checked_sub(1)?)
+    }
+
+    #[must_use]
+    pub fn iter(&self) -> Iter<'_, T> {
+        let (stack, leaf) = match &*self.data {
+            Node::Leaf(data) => (Vec::new(), Some(data.iter())),
+            Node::Branch(data) => (vec![data.iter()], None),
+        };
+        Iter {
+            stack,
+            leaf,
+            remaining: self.len,
+        }
+    }
+
+    #[must_use]
+    pub fn push_back(&self, value: T) -> Self {
+        self.push_arc(Arc::new(value))
+    }
+
+    fn push_arc(&self, value: Arc<T>) -> Self {
+        let position = self.offset + self.len;
+        let (data, shift) = if position == Node::<T>::capacity(self.shift) {
+            let mut children = sparse_vec::SparseVec::new();
+            children.insert(0, Arc::clone(&self.data));
+            (Arc::new(Node::Branch(children)), self.shift + 4)
+        } else {
+            (Arc::clone(&self.data), self.shift)
+        };
+        PerVec {
+            data: Node::set(&data, shift, position, value),
+            shift,
+            offset: self.offset,
+            len: self.len + 1,
+        }
+    }
+
+    #[must_use]
+    pub fn pop_back(&self) -> Self {
+        self.slice(..self.len.saturating_sub(1))
+    }
+
+    /// Returns `None` when `index` is out of bounds.
+    #[must_use]
+    pub fn set(&self, index: usize, value: T) -> Option<Self> {
+        (index < self.len).then(|| PerVec {
+            data: Node::set(&self.data, self.shift, self.offset + index, Arc::new(value)),
+            shift: self.shift,
+            offset: self.offset,
+            len: self.len,
+        })
+    }
+
+    /// Keeps the elements within `range`, ignoring the part of it that lies
+    /// beyond the end of the vector.
+    #[must_use]
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> Self {
+        let end = match range.end_bound() {
+            Bound::Included(end) => end.saturating_add(1),
+            Bound::Excluded(end) => *end,
+            Bound::Unbounded => self.len,
+        }
+        .min(self.len);
+        let start = match range.start_bound() {
+            Bound::Included(start) => *start,
+            Bound::Excluded(start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        }
+        .min(end);
+        if start == 0 && end == self.len {
+            return self.clone();
+        }
+        let data = Node::retain(
+            &self.data,
+            self.shift,
+            self.offset + start,
+            self.offset + end,
+        );
+        PerVec::compacted(data, self.shift, self.offset + start, end - start)
+    }
+
+    /// Appends `other`. Every node of `other` that starts where the result
+    /// needs it at a 16-aligned position is shared instead of copied, so
+    /// when `self` ends where `other`'s first leaf would begin (for example
+    /// when neither was sliced at the front and `self.len()` is a multiple
+    /// of 16) only the nodes along the seam are built anew. Otherwise the
+    /// elements of `other` are pushed one by one, in `O(m log n)`.
+    #[must_use]
+    pub fn concat(&self, other: &PerVec<T>) -> Self {
+        if self.is_empty() {
+            return other.clone();
+        }
+        let mut res = self.clone();
+        res.graft(&other.data, other.shift, 0, other);
+        res
+    }
+
+    /// Appends the elements of `other` that lie in `node`, whose first slot
+    /// is at position `base` of `other`'s tree.
+    fn graft(&mut self, node: &Arc<Node<T>>, shift: usize, base: usize, other: &PerVec<T>) {
+        let (start, end) = (other.offset, other.offset + other.len);
+        let width = Node::<T>::capacity(shift);
+        if base + width <= start || base >= end {
+            return;
+        }
+        if base >= start && (self.offset + self.len).is_multiple_of(width) {
+            *self = self.attach(Arc::clone(node), shift, width.min(end - base));
+            return;
+        }
+        match &**node {
+            Node::Leaf(data) => {
+                for index in data.keys() {
+                    if (start..end).contains(&(base + index)) {
+                        *self = self.push_arc(Arc::clone(data.get(index).unwrap()));
+                    }
+                }
+            }
+            Node::Branch(data) => {
+                for index in data.keys() {
+                    let child = data.get(index).unwrap();
+                    self.graft(child, shift - 4, base + (index << shift), other);
+                }
+            }
+        }
+    }
+
+    /// Adds `child`, a node of level `child_shift` holding `count` elements,
+    /// right after the last element. The end has to be aligned to the width
+    /// of `child`.
+    fn attach(&self, child: Arc<Node<T>>, child_shift: usize, count: usize) -> Self {
+        let position = self.offset + self.len;
+        let (mut data, mut shift) = (Arc::clone(&self.data), self.shift);
+        while Node::<T>::capacity(shift) < position + Node::<T>::capacity(child_shift) {
+            let mut children = sparse_vec::SparseVec::new();
+            children.insert(0, data);
+            data = Arc::new(Node::Branch(children));
+            shift += 4;
+        }
+        PerVec {
+            data: Node::attach(&data, shift, position, child, child_shift),
+            shift,
+            offset: self.offset,
+            len: self.len + count,
+        }
+    }
+}
+
+impl<T> Clone for PerVec<T> {
+    fn clone(&self) -> Self {
+        PerVec {
+            data: Arc::clone(&self.data),
+            shift: self.shift,
+            offset: self.offset,
+            len: self.len,
+        }
+    }
+}
+
+impl<T> Default for PerVec<T> {
+    fn default() -> Self {
+        PerVec::empty()
+    }
+}
+
+impl<T: PartialEq> PartialEq for PerVec<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len
+            && ((Arc::ptr_eq(&self.data, &other.data) && self.offset == other.offset)
+                || self.iter().eq(other))
+    }
+}
+
+impl<T: Eq> Eq for PerVec<T> {}
+
+impl<T: Hash> Hash for PerVec<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len);
+        for value in self {
+            value.hash(state);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for PerVec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = PerVec::empty();
+        vec.extend(iter);
+        vec
+    }
+}
+
+impl<T> Extend<T> for PerVec<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            *self = self.push_back(value);
+        }
+    }
+}
+
+pub struct Iter<'a, T> {
+    stack: Vec<sparse_vec::Iter<'a, 16, Arc<Node<T>>>>,
+    leaf: Option<sparse_vec::Iter<'a, 16, Arc<T>>>,
+    remaining: usize,
+}
+
+impl<'a, T> Iter<'a, T> {
+    fn next_arc(&mut self) -> Option<&'a Arc<T>> {
+        loop {
+            if let Some(value) = self.leaf.as_mut().and_then(Iterator::next) {
+                self.remaining -= 1;
+                return Some(value);
+            }
+            let next = loop {
+                let top = self.stack.last_mut()?;
+                match top.next() {
+                    Some(next) => break next,
+                    None => {
+                        self.stack.pop();
+                    }
+                }
+            };
+            match &**next {
+                Node::Leaf(data) => self.leaf = Some(data.iter()),
+                Node::Branch(data) => self.stack.push(data.iter()),
+            }
+        }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_arc().map(|value| &**value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<'a, T> IntoIterator for &'a PerVec<T> {
+    type Item = &'a T;
+
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
@@ -0,0 +1,146 @@
+use std::sync::Arc;
+
+use sparse_vec::SparseVec;
+
+#[derive(Debug)]
+pub enum Node<T> {
+    Leaf(SparseVec<16, Arc<T>>),
+    Branch(SparseVec<16, Arc<Node<T>>>),
+}
+
+impl<T> Node<T> {
+    pub fn empty(shift: usize) -> Self {
+        if shift == 0 {
+            Node::Leaf(SparseVec::new())
+        } else {
+            Node::Branch(SparseVec::new())
+        }
+    }
+
+    pub fn capacity(shift: usize) -> usize {
+        1 << (shift + 4)
+    }
+
+    pub fn index(position: usize, shift: usize) -> usize {
+        (position >> shift) & 0b1111
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            Node::Leaf(data) => data.is_empty(),
+            Node::Branch(data) => data.is_empty(),
+        }
+    }
+
+    pub fn get(&self, shift: usize, position: usize) -> Option<&Arc<T>> {
+        match self {
+            Node::Leaf(data) => data.get(Node::<T>::index(position, shift)),
+            Node::Branch(data) => data
+                .get(Node::<T>::index(position, shift))?
+                .get(shift - 4, position),
+        }
+    }
+
+    pub fn set(node: &Arc<Node<T>>, shift: usize, position: usize, value: Arc<T>) -> Arc<Node<T>> {
+        let index = Node::<T>::index(position, shift);
+        match &**node {
+            Node::Leaf(data) => {
+                let mut data = data.clone();
+                data.insert(index, value);
+                Arc::new(Node::Leaf(data))
+            }
+            Node::Branch(data) => {
+                let child = match data.get(index) {
+                    Some(child) => Node::set(child, shift - 4, position, value),
+                    None => Node::set(
+                        &Arc::new(Node::empty(shift - 4)),
+                        shift - 4,
+                        position,
+                        value,
+                    ),
+                };
+                let mut data = data.clone();
+                data.insert(index, child);
+                Arc::new(Node::Branch(data))
+            }
+        }
+    }
+
+    /// Places `child`, a node of level `child_shift`, at `position`, creating
+    /// the branches on the way down that do not exist yet.
+    pub fn attach(
+        node: &Arc<Node<T>>,
+        shift: usize,
+        position: usize,
+        child: Arc<Node<T>>,
+        child_shift: usize,
+    ) -> Arc<Node<T>> {
+        if shift == child_shift {
+            return child;
+        }
+        let Node::Branch(data) = &**node else {
+            unreachable!("leaves are never above another node");
+        };
+        let index = Node::<T>::index(position, shift);
+        let child = match data.get(index) {
+            Some(next) => Node::attach(next, shift - 4, position, child, child_shift),
+            None => Node::attach(
+                &Arc::new(Node::empty(shift - 4)),
+                shift - 4,
+                position,
+                child,
+                child_shift,
+            ),
+        };
+        let mut data = data.clone();
+        data.insert(index, child);
+        Arc::new(Node::Branch(data))
+    }
+
+    /// Keeps only the positions within `start..end`, relative to this node,
+    /// sharing every child that lies entirely inside the range. Returns `None`
+    /// when nothing is left.
+    pub fn retain(
+        node: &Arc<Node<T>>,
+        shift: usize,
+        start: usize,
+        end: usize,
+    ) -> Option<Arc<Node<T>>> {
+        if start >= end {
+            return None;
+        }
+        if start == 0 && end == Node::<T>::capacity(shift) {
+            return Some(Arc::clone(node));
+        }
+        let res = match &**node {
+            Node::Leaf(data) => {
+                let mut res = SparseVec::new();
+                for index in data.keys() {
+                    if (start..end).contains(&index) {
+                        res.insert(index, Arc::clone(data.get(index).unwrap()));
+                    }
+                }
+                Node::Leaf(res)
+            }
+            Node::Branch(data) => {
+                let width = 1 << shift;
+                let mut res = SparseVec::new();
+                for index in data.keys() {
+                    let base = index * width;
+                    let child = data.get(index).unwrap();
+                    let retained = Node::retain(
+                        child,
+                        shift - 4,
+                        start.max(base).min(base + width) - base,
+                        end.max(base).min(base + width) - base,
+                    );
+                    if let Some(retained) = retained {
+                        res.insert(index, retained);
+                    }
+                }
+                Node::Branch(res)
+            }
+        };
+        (!res.is_empty()).then(|| Arc::new(res))
+    }
+}
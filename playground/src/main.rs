@@ -2,7 +2,6 @@ use per_set::PerMap;
 
 fn main() {
     let data = [486, 693, 184];
-    let map = PerMap::empty();
-    let map = data.iter().fold(map, |m, e| m.insert(*e, *e));
+    let map = data.iter().map(|e| (*e, *e)).collect::<PerMap<_, _>>();
     println!("{:?}", map.get(&693));
 }
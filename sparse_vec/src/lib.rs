@@ -2,12 +2,14 @@ use std::mem;
 
 use smallvec::SmallVec;
 
+#[cfg(feature = "serde")]
+mod serde_impl;
 #[cfg(test)]
 mod tests;
 
 #[derive(Debug)]
 pub struct SparseVec<const CAP: usize, T> {
-    mask: usize,
+    mask: u64,
     data: SmallVec<[T; 4]>,
 }
 
@@ -35,6 +37,16 @@ impl<const CAP: usize, T> SparseVec<CAP, T> {
         self.mask == 0
     }
 
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.data.capacity()
+    }
+
+    #[must_use]
+    pub fn spilled(&self) -> bool {
+        self.data.spilled()
+    }
+
     pub fn insert(&mut self, pos: usize, elem: T) {
         let real_pos = self.elems_before(pos);
         let already_present = self.mask & (1 << (CAP - pos - 1)) != 0;
@@ -55,6 +67,16 @@ impl<const CAP: usize, T> SparseVec<CAP, T> {
         }
     }
 
+    #[must_use]
+    pub fn get_mut(&mut self, pos: usize) -> Option<&mut T> {
+        if self.mask & (1 << (CAP - pos - 1)) != 0 {
+            let real_pos = self.elems_before(pos);
+            Some(&mut self.data[real_pos])
+        } else {
+            None
+        }
+    }
+
     pub fn remove(&mut self, pos: usize) -> Option<T> {
         if self.mask & (1 << (CAP - pos - 1)) != 0 {
             let real_pos = self.elems_before(pos);
@@ -93,7 +115,10 @@ impl<const CAP: usize, T> SparseVec<CAP, T> {
     }
 
     fn elems_before(&self, pos: usize) -> usize {
-        (self.mask >> (CAP - pos)).count_ones() as usize
+        self.mask
+            .checked_shr((CAP - pos) as u32)
+            .unwrap_or(0)
+            .count_ones() as usize
     }
 }
 
@@ -116,6 +141,32 @@ impl<'a, const CAP: usize, T> IntoIterator for &'a SparseVec<CAP, T> {
     }
 }
 
+impl<const CAP: usize, T> IntoIterator for SparseVec<CAP, T> {
+    type Item = T;
+
+    type IntoIter = IntoIter<CAP, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self.data.into_iter())
+    }
+}
+
+pub struct IntoIter<const CAP: usize, T>(smallvec::IntoIter<[T; 4]>);
+
+impl<const CAP: usize, T> Iterator for IntoIter<CAP, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<const CAP: usize, T> ExactSizeIterator for IntoIter<CAP, T> {}
+
 pub struct Iter<'a, const CAP: usize, T> {
     index: usize,
     vector: &'a SparseVec<CAP, T>,
@@ -0,0 +1,58 @@
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use smallvec::SmallVec;
+
+use crate::SparseVec;
+
+#[derive(Serialize)]
+#[serde(rename = "SparseVec")]
+struct Borrowed<'a, T> {
+    mask: u64,
+    values: &'a [T],
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "SparseVec")]
+struct Owned<T> {
+    mask: u64,
+    values: Vec<T>,
+}
+
+impl<const CAP: usize, T: Serialize> Serialize for SparseVec<CAP, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Borrowed {
+            mask: reorder::<CAP>(self.mask),
+            values: &self.data,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, const CAP: usize, T: Deserialize<'de>> Deserialize<'de> for SparseVec<CAP, T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let Owned { mask, values } = Owned::deserialize(deserializer)?;
+        if CAP < u64::BITS as usize && mask >> CAP != 0 {
+            return Err(D::Error::custom(format!(
+                "mask {mask:#x} has bits set beyond capacity {CAP}"
+            )));
+        }
+        if values.len() != mask.count_ones() as usize {
+            return Err(D::Error::invalid_length(
+                values.len(),
+                &"as many values as bits set in the mask",
+            ));
+        }
+        Ok(SparseVec {
+            mask: reorder::<CAP>(mask),
+            data: SmallVec::from_vec(values),
+        })
+    }
+}
+
+/// Converts between the serialized mask, where bit `p` stands for position
+/// `p`, and the one kept in memory, where position `p` is bit `CAP - p - 1`.
+/// The conversion is its own inverse.
+fn reorder<const CAP: usize>(mask: u64) -> u64 {
+    mask.reverse_bits()
+        .checked_shr(u64::BITS - CAP as u32)
+        .unwrap_or(0)
+}
@@ -43,6 +43,22 @@ proptest! {
         }
     }
 
+    #[test]
+    fn elements_can_be_modified_in_place((elems, selected) in map_with_selected(5, 0usize..16)) {
+        let mut sparse_vec = SparseVec::<16, String>::new();
+        for (pos, elem) in &elems {
+            sparse_vec.insert(*pos, elem.clone());
+        }
+
+        sparse_vec.get_mut(selected).unwrap().push('!');
+        prop_assert_eq!(Some(&format!("{}!", elems[&selected])), sparse_vec.get(selected));
+        for e in elems.keys().filter(|e| **e != selected) {
+            prop_assert_eq!(elems.get(e), sparse_vec.get(*e));
+        }
+        let non_present = (0usize..16).find(|i| !elems.contains_key(i)).unwrap();
+        prop_assert_eq!(None, sparse_vec.get_mut(non_present));
+    }
+
     #[test]
     fn elemenets_can_be_swapped((elems, selected) in map_with_selected(5, 0usize..16)) {
         let mut sparse_vec = SparseVec::<16, String>::new();
@@ -120,4 +136,67 @@ proptest! {
         prop_assert_eq!(expected, res);
 
     }
+
+    #[test]
+    fn full_width_vectors_keep_every_position(elems in hash_map(0usize..64, ".*", 0usize..40)) {
+        let mut sparse_vec = SparseVec::<64, String>::new();
+        for (pos, elem) in &elems {
+            sparse_vec.insert(*pos, elem.clone());
+        }
+        prop_assert_eq!(elems.len(), sparse_vec.len());
+        for (pos, elem) in &elems {
+            prop_assert_eq!(Some(elem), sparse_vec.get(*pos));
+        }
+
+        let mut expected = elems.keys().copied().collect::<Vec<_>>();
+        expected.sort_unstable();
+        prop_assert_eq!(expected, sparse_vec.keys());
+    }
+
+    #[test]
+    fn owned_iteration_returns_correctly_ordered_elements(elems in hash_map(0usize..16, ".*", 0usize..5)) {
+        let mut sparse_vec = SparseVec::<16, String>::new();
+        for (pos, elem) in &elems {
+            sparse_vec.insert(*pos, elem.clone());
+        }
+
+        let res = sparse_vec.into_iter().collect::<Vec<_>>();
+
+        let mut expected = elems.into_iter().collect::<Vec<_>>();
+        expected.sort();
+        let expected = expected.into_iter().map(|(_, e)| e).collect::<Vec<_>>();
+
+        prop_assert_eq!(expected, res);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_round_trip_keeps_positions(elems in hash_map(0usize..16, ".*", 0usize..5)) {
+        let mut sparse_vec = SparseVec::<16, String>::new();
+        for (pos, elem) in &elems {
+            sparse_vec.insert(*pos, elem.clone());
+        }
+
+        let json = serde_json::to_string(&sparse_vec).unwrap();
+        let res = serde_json::from_str::<SparseVec<16, String>>(&json).unwrap();
+
+        prop_assert_eq!(sparse_vec.keys(), res.keys());
+        for (pos, elem) in &elems {
+            prop_assert_eq!(Some(elem), res.get(*pos));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_rejects_masks_that_do_not_match_values(mask in 0usize..1 << 20, len in 0usize..20) {
+        let json = format!(r#"{{"mask":{mask},"values":{:?}}}"#, vec![0u8; len]);
+        let res = serde_json::from_str::<SparseVec<16, u8>>(&json);
+        prop_assert_eq!(mask < 1 << 16 && mask.count_ones() as usize == len, res.is_ok());
+        if let Ok(res) = res {
+            let positions = (0..16).filter(|p| mask & 1 << p != 0).collect::<Vec<_>>();
+            prop_assert_eq!(positions, res.keys());
+            let json = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+            prop_assert_eq!(json, serde_json::to_value(&res).unwrap());
+        }
+    }
 }